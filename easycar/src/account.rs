@@ -0,0 +1,199 @@
+use std::{
+    sync::{Arc, RwLock},
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Utc};
+use info_car_api::{error::GenericClientError, types::ReservationList};
+use teloxide::{types::ChatId, Bot};
+use tokio::sync::Mutex;
+
+use crate::{
+    client::{InfoCarClient, UserData},
+    config::{AccountConfig, Config, WatchConfig},
+    health::{Health, HealthReport},
+    i18n::Language,
+    notifier::{Notification, NotificationRouter, NotifyError},
+    service::{pages::PageCache, NewServiceError},
+    state::{PaymentReminder, StateError, StateStore},
+};
+
+/// A logged in info-car account together with its Telegram chat and persistent state
+pub struct Account {
+    pub name: String,
+    /// Not set in the headless mode
+    pub chat_id: Option<ChatId>,
+    pub client: Arc<Mutex<InfoCarClient>>,
+    pub state: Arc<Mutex<StateStore>>,
+    pub health: Health,
+    /// The lists shown by the paged messages
+    pub pages: PageCache,
+    /// The last reservations fetched by the workers and when
+    reservations: Mutex<Option<(Instant, Arc<ReservationList>)>>,
+    bot: Option<Arc<Bot>>,
+    notifier: RwLock<Arc<NotificationRouter>>,
+    language: RwLock<Language>,
+}
+
+impl Account {
+    /// Logs in to the account. Notifications can be sent to telegram only if the `bot` is given.
+    pub async fn new(
+        config: &Config,
+        account: &AccountConfig,
+        bot: Option<Arc<Bot>>,
+    ) -> Result<Self, NewServiceError> {
+        let notifier = NotificationRouter::new(config, account, bot.as_ref())?;
+        let state = StateStore::load(config.storage.state_file(&account.name))?;
+        let criteria = watch_criteria(state.state().watch_override.as_ref(), config, account);
+        let client = InfoCarClient::new(
+            UserData::new(account.username.clone(), account.password.clone()),
+            account.pesel.clone(),
+            account.phone_number.clone(),
+            account.driver_profile.clone(),
+            criteria,
+        )
+        .await?;
+        log::info!("Logged in as {}", account.name);
+        let health = Health::new(client.get_token_expire_date());
+        let language = state.state().language.unwrap_or(account.language);
+
+        Ok(Self {
+            name: account.name.clone(),
+            chat_id: account.chat_id.map(ChatId),
+            client: Arc::new(Mutex::new(client)),
+            state: Arc::new(Mutex::new(state)),
+            health,
+            pages: PageCache::default(),
+            reservations: Mutex::new(None),
+            bot,
+            notifier: RwLock::new(Arc::new(notifier)),
+            language: RwLock::new(language),
+        })
+    }
+
+    /// Builds the notifiers of this account from the configuration
+    pub fn build_notifier(&self, config: &Config) -> Result<NotificationRouter, NotifyError> {
+        match config.account(&self.name) {
+            Some(account) => NotificationRouter::new(config, account, self.bot.as_ref()),
+            None => Ok(NotificationRouter::default()),
+        }
+    }
+
+    pub fn set_notifier(&self, notifier: NotificationRouter) {
+        *self.notifier.write().expect("Notifier lock poisoned") = Arc::new(notifier);
+    }
+
+    /// Sends the notification to the notifiers of this account
+    pub async fn notify(&self, notification: Notification) -> Result<(), NotifyError> {
+        let notifier = Arc::clone(&self.notifier.read().expect("Notifier lock poisoned"));
+        notifier.send(&notification).await
+    }
+
+    /// Language of the messages sent to this account
+    pub fn language(&self) -> Language {
+        *self.language.read().expect("Language lock poisoned")
+    }
+
+    /// Saves the language chosen from Telegram. `None` goes back to the configured language.
+    pub async fn set_language(
+        &self,
+        language: Option<Language>,
+        config: &Config,
+    ) -> Result<Language, StateError> {
+        self.state
+            .lock()
+            .await
+            .update(|state| state.language = language)
+            .await?;
+        self.apply_config(config).await;
+        Ok(self.language())
+    }
+
+    /// Returns the reservations fetched within `max_age`, or fetches them again. The workers share
+    /// them, so each of them doesn't request the same list on its own timer.
+    pub async fn reservations(
+        &self,
+        max_age: Duration,
+    ) -> Result<Arc<ReservationList>, GenericClientError> {
+        // Held during the request, so the workers waking up together wait for a single one
+        let mut cached = self.reservations.lock().await;
+        if let Some((fetched, reservations)) = cached.as_ref() {
+            if fetched.elapsed() < max_age {
+                return Ok(Arc::clone(reservations));
+            }
+        }
+        let reservations = Arc::new(self.client.lock().await.get_reservations().await?);
+        *cached = Some((Instant::now(), Arc::clone(&reservations)));
+        Ok(reservations)
+    }
+
+    /// Remembers the payment deadline of a new reservation for the payment reminders. Returns the
+    /// deadline.
+    pub async fn record_enrollment(&self, reservation_id: &str, config: &Config) -> DateTime<Utc> {
+        let deadline = Utc::now() + config.reminders.payment_window.0;
+        if let Err(err) = self
+            .state
+            .lock()
+            .await
+            .update(|state| {
+                state
+                    .payment_reminders
+                    .insert(reservation_id.to_owned(), PaymentReminder::new(deadline))
+            })
+            .await
+        {
+            log::error!("[{}] Failed to save the payment deadline: {err}", self.name);
+        }
+        deadline
+    }
+
+    pub fn health_report(&self, config: &Config) -> HealthReport {
+        self.health.report(config.poll_intervals.exams.0)
+    }
+
+    /// Applies the watch criteria and the language from the configuration unless they were
+    /// changed from Telegram
+    pub async fn apply_config(&self, config: &Config) {
+        let Some(account) = config.account(&self.name) else {
+            return;
+        };
+        let (criteria, language) = {
+            let state = self.state.lock().await;
+            let state = state.state();
+            (
+                watch_criteria(state.watch_override.as_ref(), config, account),
+                state.language.unwrap_or(account.language),
+            )
+        };
+        *self.language.write().expect("Language lock poisoned") = language;
+        self.client.lock().await.set_criteria(criteria);
+    }
+}
+
+/// Returns the criteria changed from Telegram, or the configured ones if there are none. Overrides
+/// saved by older versions may be invalid, then they are ignored.
+fn watch_criteria(
+    watch_override: Option<&WatchConfig>,
+    config: &Config,
+    account: &AccountConfig,
+) -> WatchConfig {
+    match watch_override.map(|criteria| (criteria, criteria.validate("watch_override"))) {
+        Some((criteria, Ok(()))) => criteria.clone(),
+        Some((_, Err(err))) => {
+            log::warn!(
+                "[{}] Ignoring the saved watch criteria: {err}",
+                account.name
+            );
+            config.watch(account)
+        }
+        None => config.watch(account),
+    }
+}
+
+/// Returns the account linked to the chat
+pub fn find_account(accounts: &[Arc<Account>], chat_id: ChatId) -> Option<Arc<Account>> {
+    accounts
+        .iter()
+        .find(|account| account.chat_id == Some(chat_id))
+        .cloned()
+}
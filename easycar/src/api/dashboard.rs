@@ -0,0 +1,829 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Write,
+    sync::{Arc, Mutex},
+};
+
+use axum::{
+    extract::{Path, Request, State},
+    http::{header, HeaderMap, StatusCode},
+    middleware::{self, Next},
+    response::{Html, IntoResponse, Redirect, Response},
+    routing::{get, post},
+    Form, Router,
+};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveDateTime, Utc};
+use chrono_tz::Europe::Warsaw;
+use serde::Deserialize;
+use subtle::ConstantTimeEq;
+use teloxide::utils::html::escape;
+
+use super::ApiState;
+use crate::{
+    account::Account,
+    client::{FoundExam, GetExamsError},
+    config::DashboardConfig,
+    utils::{plain_date, plain_date_from_string},
+};
+
+const SESSION_COOKIE: &str = "easycar_session";
+/// How long a login is valid
+const SESSION_LIFETIME: Duration = Duration::days(7);
+/// The oldest sessions are dropped above this number
+const MAX_SESSIONS: usize = 100;
+/// Failed logins allowed before the next ones are delayed
+const FREE_LOGIN_ATTEMPTS: u32 = 3;
+/// The longest delay after the failed logins
+const MAX_LOGIN_DELAY: Duration = Duration::minutes(5);
+/// Number of the closest exams listed below the heatmap
+const LISTED_EXAMS: usize = 20;
+/// Number of the latest exam appearances shown
+const LISTED_HISTORY: usize = 50;
+
+const STYLE: &str = "
+body { font-family: sans-serif; margin: 2em auto; max-width: 64em; padding: 0 1em; color: #222; }
+table { border-collapse: collapse; margin-bottom: 1em; }
+th, td { padding: 0.3em 0.6em; border-bottom: 1px solid #ddd; text-align: left; }
+.heatmap td { width: 2.5em; height: 2.5em; text-align: center; border: 1px solid #fff; font-size: 0.8em; }
+.notice { padding: 0.6em; background: #eef6ee; border: 1px solid #9c9; }
+.error { padding: 0.6em; background: #fbeaea; border: 1px solid #c99; }
+header { display: flex; justify-content: space-between; align-items: center; }
+form.inline { display: inline; }
+";
+
+/// Logged in browser sessions of the dashboard
+#[derive(Clone)]
+pub(super) struct Dashboard {
+    login: DashboardConfig,
+    /// Session ids and their expiry dates
+    sessions: Arc<Mutex<HashMap<String, DateTime<Utc>>>>,
+    attempts: Arc<Mutex<LoginAttempts>>,
+}
+
+/// Failed logins since the last successful one, which throttle the password guessing
+#[derive(Default)]
+struct LoginAttempts {
+    failed: u32,
+    blocked_until: Option<DateTime<Utc>>,
+}
+
+impl Dashboard {
+    pub(super) fn new(login: DashboardConfig) -> Self {
+        Self {
+            login,
+            sessions: Arc::new(Mutex::new(HashMap::new())),
+            attempts: Arc::default(),
+        }
+    }
+
+    fn attempts(&self) -> std::sync::MutexGuard<'_, LoginAttempts> {
+        self.attempts.lock().expect("Login attempts lock poisoned")
+    }
+
+    /// Time left until the next login can be tried
+    fn login_delay(&self, now: DateTime<Utc>) -> Option<Duration> {
+        self.attempts()
+            .blocked_until
+            .filter(|until| *until > now)
+            .map(|until| until - now)
+    }
+
+    /// Doubles the delay of the next login with every failed one above [`FREE_LOGIN_ATTEMPTS`]
+    fn login_failed(&self, now: DateTime<Utc>) {
+        let mut attempts = self.attempts();
+        attempts.failed += 1;
+        if let Some(exceeded) = attempts.failed.checked_sub(FREE_LOGIN_ATTEMPTS) {
+            let delay = Duration::seconds(1 << exceeded.min(16)).min(MAX_LOGIN_DELAY);
+            attempts.blocked_until = Some(now + delay);
+        }
+    }
+
+    fn login_succeeded(&self) {
+        *self.attempts() = LoginAttempts::default();
+    }
+
+    fn sessions(&self) -> std::sync::MutexGuard<'_, HashMap<String, DateTime<Utc>>> {
+        self.sessions.lock().expect("Sessions lock poisoned")
+    }
+
+    /// Checks the credentials without leaking through the timing which part was wrong
+    fn check_login(&self, username: &str, password: &str) -> bool {
+        let username = username.as_bytes().ct_eq(self.login.username.as_bytes());
+        let password = password.as_bytes().ct_eq(self.login.password.as_bytes());
+        (username & password).into()
+    }
+
+    /// Starts a new session, forgetting the expired ones
+    fn start_session(&self) -> String {
+        let now = Utc::now();
+        let mut sessions = self.sessions();
+        sessions.retain(|_, expires| *expires > now);
+        while sessions.len() >= MAX_SESSIONS {
+            let Some(oldest) = sessions
+                .iter()
+                .min_by_key(|(_, expires)| **expires)
+                .map(|(id, _)| id.clone())
+            else {
+                break;
+            };
+            sessions.remove(&oldest);
+        }
+        let id = new_session_id();
+        sessions.insert(id.clone(), now + SESSION_LIFETIME);
+        id
+    }
+
+    fn is_logged_in(&self, id: &str) -> bool {
+        let mut sessions = self.sessions();
+        match sessions.get(id) {
+            Some(expires) if *expires > Utc::now() => true,
+            Some(_) => {
+                sessions.remove(id);
+                false
+            }
+            None => false,
+        }
+    }
+}
+
+pub(super) fn router(state: ApiState) -> Router<ApiState> {
+    let pages = Router::new()
+        .route("/dashboard", get(index))
+        .route("/dashboard/{account}", get(account_page))
+        .route("/dashboard/{account}/enroll/{exam_id}", post(enroll))
+        .route("/dashboard/{account}/cancel/{reservation_id}", post(cancel))
+        .route("/logout", post(logout))
+        .route_layer(middleware::from_fn_with_state(state, require_login));
+
+    Router::new()
+        .route("/", get(|| async { Redirect::to("/dashboard") }))
+        .route("/login", get(login_page).post(login))
+        .merge(pages)
+}
+
+fn session_id(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .find_map(|cookie| cookie.trim().strip_prefix(&format!("{SESSION_COOKIE}=")))
+}
+
+/// Attributes of the session cookie. `Secure` is only set behind TLS, because the browsers drop
+/// such cookies over plain HTTP.
+fn cookie_attributes(login: &DashboardConfig, headers: &HeaderMap) -> String {
+    let https = headers
+        .get("x-forwarded-proto")
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|proto| proto.eq_ignore_ascii_case("https"));
+    let secure = if login.secure_cookie || https {
+        "; Secure"
+    } else {
+        ""
+    };
+    format!("Path=/; HttpOnly; SameSite=Strict{secure}")
+}
+
+fn new_session_id() -> String {
+    let mut bytes = [0u8; 32];
+    getrandom::fill(&mut bytes).expect("The system random number generator failed");
+    bytes.iter().fold(String::new(), |mut id, byte| {
+        let _ = write!(id, "{byte:02x}");
+        id
+    })
+}
+
+/// Redirects to the login page without a valid session
+async fn require_login(State(state): State<ApiState>, request: Request, next: Next) -> Response {
+    let logged_in = match (&state.dashboard, session_id(request.headers())) {
+        (Some(dashboard), Some(id)) => dashboard.is_logged_in(id),
+        _ => false,
+    };
+    if !logged_in {
+        return Redirect::to("/login").into_response();
+    }
+    next.run(request).await
+}
+
+fn page(title: &str, body: &str) -> Html<String> {
+    Html(format!(
+        "<!DOCTYPE html>
+<html>
+<head>
+<meta charset=\"utf-8\">
+<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">
+<title>{} - EasyCar</title>
+<style>{STYLE}</style>
+</head>
+<body>
+{body}
+</body>
+</html>",
+        escape(title)
+    ))
+}
+
+fn login_form(error: Option<&str>) -> Html<String> {
+    let error = error
+        .map(|error| format!("<p class=\"error\">{}</p>", escape(error)))
+        .unwrap_or_default();
+    page(
+        "Log in",
+        &format!(
+            "<h1>EasyCar</h1>
+{error}
+<form method=\"post\" action=\"/login\">
+<p><label>Username <input name=\"username\" autocomplete=\"username\" required></label></p>
+<p><label>Password <input name=\"password\" type=\"password\" autocomplete=\"current-password\" required></label></p>
+<p><button type=\"submit\">Log in</button></p>
+</form>"
+        ),
+    )
+}
+
+async fn login_page() -> Html<String> {
+    login_form(None)
+}
+
+#[derive(Deserialize)]
+struct LoginForm {
+    username: String,
+    password: String,
+}
+
+async fn login(
+    State(state): State<ApiState>,
+    headers: HeaderMap,
+    Form(form): Form<LoginForm>,
+) -> Response {
+    let Some(dashboard) = &state.dashboard else {
+        return Redirect::to("/login").into_response();
+    };
+    let now = Utc::now();
+    if let Some(delay) = dashboard.login_delay(now) {
+        // Rounded up, so retrying after the given time succeeds
+        let seconds = delay.num_seconds() + 1;
+        return (
+            StatusCode::TOO_MANY_REQUESTS,
+            [(header::RETRY_AFTER, seconds.to_string())],
+            login_form(Some(&format!(
+                "Too many failed logins, try again in {seconds} seconds"
+            ))),
+        )
+            .into_response();
+    }
+    if !dashboard.check_login(&form.username, &form.password) {
+        log::warn!("Failed dashboard login as '{}'", form.username);
+        dashboard.login_failed(now);
+        return login_form(Some("Invalid username or password")).into_response();
+    }
+    dashboard.login_succeeded();
+
+    let id = dashboard.start_session();
+    (
+        [(
+            header::SET_COOKIE,
+            format!(
+                "{SESSION_COOKIE}={id}; {}; Max-Age={}",
+                cookie_attributes(&dashboard.login, &headers),
+                SESSION_LIFETIME.num_seconds()
+            ),
+        )],
+        Redirect::to("/dashboard"),
+    )
+        .into_response()
+}
+
+async fn logout(State(state): State<ApiState>, headers: HeaderMap) -> Response {
+    let Some(dashboard) = &state.dashboard else {
+        return Redirect::to("/login").into_response();
+    };
+    if let Some(id) = session_id(&headers) {
+        dashboard.sessions().remove(id);
+    }
+    (
+        [(
+            header::SET_COOKIE,
+            format!(
+                "{SESSION_COOKIE}=; {}; Max-Age=0",
+                cookie_attributes(&dashboard.login, &headers)
+            ),
+        )],
+        Redirect::to("/login"),
+    )
+        .into_response()
+}
+
+const LOGOUT_BUTTON: &str = "<form class=\"inline\" method=\"post\" action=\"/logout\">\
+<button type=\"submit\">Log out</button></form>";
+
+async fn index(State(state): State<ApiState>) -> Response {
+    if let [account] = state.accounts.as_slice() {
+        return Redirect::to(&format!("/dashboard/{}", account.name)).into_response();
+    }
+    let mut body = format!("<header><h1>EasyCar</h1>{LOGOUT_BUTTON}</header><ul>");
+    for account in state.accounts.iter() {
+        let name = escape(&account.name);
+        let _ = write!(body, "<li><a href=\"/dashboard/{name}\">{name}</a></li>");
+    }
+    body += "</ul>";
+    page("Accounts", &body).into_response()
+}
+
+fn not_found(account: &str) -> Response {
+    page(
+        "Not found",
+        &format!(
+            "<p class=\"error\">Unknown account {}</p><p><a href=\"/dashboard\">Back</a></p>",
+            escape(account)
+        ),
+    )
+    .into_response()
+}
+
+/// Shows the result of an action together with a link back to the account
+fn result_page(account: &str, class: &str, message: &str) -> Response {
+    let name = escape(account);
+    page(
+        account,
+        &format!(
+            "<p class=\"{class}\">{}</p><p><a href=\"/dashboard/{name}\">Back to {name}</a></p>",
+            escape(message)
+        ),
+    )
+    .into_response()
+}
+
+async fn account_page(State(state): State<ApiState>, Path(name): Path<String>) -> Response {
+    let Ok(account) = state.account(&name) else {
+        return not_found(&name);
+    };
+    let mut body = format!(
+        "<header><h1>{}</h1>{LOGOUT_BUTTON}</header>",
+        escape(&account.name)
+    );
+    body += &watched_words(account).await;
+    body += &free_slots(account).await;
+    body += &reservations(account).await;
+    body += &history(account).await;
+    page(&account.name, &body).into_response()
+}
+
+fn section_error(err: impl std::fmt::Display) -> String {
+    format!("<p class=\"error\">{}</p>", escape(&err.to_string()))
+}
+
+async fn watched_words(account: &Account) -> String {
+    let client = account.client.lock().await;
+    let criteria = client.criteria().clone();
+    let mut html = format!(
+        "<h2>Watched WORDs</h2><p>Categories: {}. Exams: {}. Horizon: {}. Filter: {}.</p>",
+        escape(&join(&criteria.categories)),
+        escape(&join(&criteria.exam_kinds)),
+        criteria.horizon,
+        escape(&criteria.filter.to_string())
+    );
+    match client.watched_words().await {
+        Ok(words) => {
+            html += "<table><tr><th>ID</th><th>Name</th><th>Address</th></tr>";
+            for id in &criteria.words {
+                match words.iter().find(|word| word.id == *id) {
+                    Some(word) => {
+                        let _ = write!(
+                            html,
+                            "<tr><td>{id}</td><td>{}</td><td>{}</td></tr>",
+                            escape(&word.name),
+                            escape(&word.address)
+                        );
+                    }
+                    None => {
+                        let _ =
+                            write!(html, "<tr><td>{id}</td><td colspan=\"2\">Unknown</td></tr>");
+                    }
+                }
+            }
+            html += "</table>";
+        }
+        Err(err) => html += &section_error(err),
+    }
+    html
+}
+
+fn join<T: std::fmt::Display>(items: &[T]) -> String {
+    items
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn exam_day(exam: &FoundExam) -> Option<NaiveDate> {
+    NaiveDateTime::parse_from_str(&exam.exam.date, "%Y-%m-%dT%H:%M:%S")
+        .map(|date| date.date())
+        .ok()
+}
+
+async fn free_slots(account: &Account) -> String {
+    let mut client = account.client.lock().await;
+    let horizon = client.criteria().horizon.0;
+    let exams = match client.get_nearest_exams(usize::MAX).await {
+        Ok(exams) => exams,
+        Err(GetExamsError::NoExamsError) => Vec::new(),
+        Err(err) => return format!("<h2>Free slots</h2>{}", section_error(err)),
+    };
+    drop(client);
+
+    let mut html = "<h2>Free slots</h2>".to_owned();
+    html += &heatmap(&exams, horizon);
+
+    if exams.is_empty() {
+        html += "<p>No exams meet the criteria</p>";
+        return html;
+    }
+    html += "<table><tr><th>Date</th><th>Exam</th><th>WORD</th><th>Places</th><th>Price</th><th></th></tr>";
+    for found in exams.iter().take(LISTED_EXAMS) {
+        let _ = write!(
+            html,
+            "<tr><td>{}</td><td>{} {}</td><td>{}</td><td>{}</td><td>{} PLN</td><td>\
+<form class=\"inline\" method=\"post\" action=\"/dashboard/{}/enroll/{}\" \
+onsubmit=\"return confirm('Enroll to this exam?')\"><button type=\"submit\">Enroll</button></form>\
+</td></tr>",
+            plain_date_from_string(&found.exam.date),
+            found.kind,
+            found.category,
+            found.word_id,
+            found.exam.places,
+            found.exam.amount,
+            escape(&account.name),
+            escape(&found.exam.id)
+        );
+    }
+    html += "</table>";
+    html
+}
+
+/// Renders the free places per day as a calendar, a week per row
+fn heatmap(exams: &[FoundExam], horizon: Duration) -> String {
+    let mut places = BTreeMap::<NaiveDate, i32>::new();
+    for found in exams {
+        if let Some(day) = exam_day(found) {
+            *places.entry(day).or_default() += found.exam.places.max(1);
+        }
+    }
+    let max = places.values().copied().max().unwrap_or(1);
+
+    let today = Utc::now().with_timezone(&Warsaw).date_naive();
+    let last = places
+        .keys()
+        .next_back()
+        .copied()
+        .unwrap_or(today)
+        .max(today.checked_add_signed(horizon).unwrap_or(today));
+    let mut day = today - Duration::days(today.weekday().num_days_from_monday().into());
+
+    let mut html = "<table class=\"heatmap\"><tr>".to_owned();
+    for weekday in ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"] {
+        let _ = write!(html, "<th>{weekday}</th>");
+    }
+    html += "</tr>";
+    while day <= last {
+        html += "<tr>";
+        for _ in 0..7 {
+            let count = places.get(&day).copied().unwrap_or(0);
+            let color = if day < today {
+                "#f4f4f4".to_owned()
+            } else if count == 0 {
+                "#ebedf0".to_owned()
+            } else {
+                // From light to dark green
+                let lightness = 80 - 50 * count / max;
+                format!("hsl(130, 55%, {lightness}%)")
+            };
+            let _ = write!(
+                html,
+                "<td style=\"background: {color}\" title=\"{day}: {count} free places\">{}</td>",
+                day.day()
+            );
+            day += Duration::days(1);
+        }
+        html += "</tr>";
+    }
+    html += "</table>";
+    html
+}
+
+async fn reservations(account: &Account) -> String {
+    let reservations = match account.client.lock().await.get_reservations().await {
+        Ok(reservations) => reservations,
+        Err(err) => return format!("<h2>Reservations</h2>{}", section_error(err)),
+    };
+    let mut html = "<h2>Reservations</h2>".to_owned();
+    if reservations.items.is_empty() {
+        html += "<p>There are no reservations</p>";
+        return html;
+    }
+    html += "<table><tr><th>ID</th><th>Date</th><th>Exam</th><th>WORD</th><th>Status</th><th></th></tr>";
+    for item in &reservations.items {
+        let exam = item.exam.practice.as_ref().or(item.exam.theory.as_ref());
+        let cancel = if item.status.status.is_active() {
+            format!(
+                "<form class=\"inline\" method=\"post\" action=\"/dashboard/{}/cancel/{}\" \
+onsubmit=\"return confirm('Cancel this reservation?')\"><button type=\"submit\">Cancel</button></form>",
+                escape(&account.name),
+                escape(&item.id)
+            )
+        } else {
+            String::new()
+        };
+        let _ = write!(
+            html,
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}, {}</td><td>{}</td><td>{cancel}</td></tr>",
+            escape(&item.id),
+            exam.map(|exam| plain_date_from_string(&exam.date))
+                .unwrap_or("unknown date".to_owned()),
+            item.exam.category,
+            escape(&item.exam.organization_unit_name),
+            escape(&item.exam.address),
+            item.status.status
+        );
+    }
+    html += "</table>";
+    html
+}
+
+async fn history(account: &Account) -> String {
+    let state = account.state.lock().await;
+    let history = &state.state().exam_history;
+    let mut html = "<h2>Recently appeared exams</h2>".to_owned();
+    if history.is_empty() {
+        html += "<p>No new exams were seen yet</p>";
+        return html;
+    }
+    html += "<table><tr><th>Seen</th><th>Date</th><th>Exam</th><th>WORD</th><th>Places</th></tr>";
+    for appearance in history.iter().rev().take(LISTED_HISTORY) {
+        let found = &appearance.exam;
+        let _ = write!(
+            html,
+            "<tr><td>{}</td><td>{}</td><td>{} {}</td><td>{}</td><td>{}</td></tr>",
+            plain_date(appearance.seen),
+            plain_date_from_string(&found.exam.date),
+            found.kind,
+            found.category,
+            found.word_id,
+            found.exam.places
+        );
+    }
+    html += "</table>";
+    html
+}
+
+async fn enroll(
+    State(state): State<ApiState>,
+    Path((name, exam_id)): Path<(String, String)>,
+) -> Response {
+    let Ok(account) = state.account(&name) else {
+        return not_found(&name);
+    };
+    let mut client = account.client.lock().await;
+    // Look the exam up first, so the reservation is made in the right WORD
+    if let Err(err) = client.get_nearest_exams(usize::MAX).await {
+        log::warn!("[{}] Failed to look up the exam: {err}", account.name);
+    }
+    match client.enroll(exam_id.clone()).await {
+        Ok(reservation_id) => {
+            drop(client);
+            log::info!(
+                "[{}] Enrolled from the dashboard: {reservation_id}",
+                account.name
+            );
+            account
+                .record_enrollment(&reservation_id, &state.config.current())
+                .await;
+            result_page(
+                &account.name,
+                "notice",
+                &format!("Enrolled to exam {exam_id}. Reservation ID: {reservation_id}"),
+            )
+        }
+        Err(err) => result_page(
+            &account.name,
+            "error",
+            &format!("Failed to enroll to exam {exam_id}: {err}"),
+        ),
+    }
+}
+
+async fn cancel(
+    State(state): State<ApiState>,
+    Path((name, reservation_id)): Path<(String, String)>,
+) -> Response {
+    let Ok(account) = state.account(&name) else {
+        return not_found(&name);
+    };
+    match account
+        .client
+        .lock()
+        .await
+        .cancel(reservation_id.clone())
+        .await
+    {
+        Ok(()) => {
+            log::info!(
+                "[{}] Cancelled from the dashboard: {reservation_id}",
+                account.name
+            );
+            result_page(
+                &account.name,
+                "notice",
+                &format!("Cancelled reservation {reservation_id}"),
+            )
+        }
+        Err(err) => result_page(
+            &account.name,
+            "error",
+            &format!("Failed to cancel reservation {reservation_id}: {err}"),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroU32;
+
+    use axum::http::HeaderValue;
+    use info_car_api::types::{Exam, LicenseCategory};
+
+    use super::*;
+    use crate::config::ExamKind;
+
+    fn dashboard(secure_cookie: bool) -> Dashboard {
+        Dashboard::new(DashboardConfig {
+            username: "admin".to_owned(),
+            password: "secret".to_owned(),
+            secure_cookie,
+        })
+    }
+
+    fn exam(day: NaiveDate, places: i32) -> FoundExam {
+        FoundExam {
+            exam: Exam {
+                id: format!("{day}-{places}"),
+                places,
+                date: format!("{day}T08:00:00"),
+                amount: 200,
+                additional_info: None,
+            },
+            word_id: NonZeroU32::MIN,
+            category: LicenseCategory::B,
+            kind: ExamKind::Practice,
+        }
+    }
+
+    fn free_places(html: &str, day: NaiveDate) -> Option<&str> {
+        let title = format!("title=\"{day}: ");
+        let start = html.find(&title)? + title.len();
+        html[start..].split(' ').next()
+    }
+
+    #[test]
+    fn checks_login() {
+        let dashboard = dashboard(false);
+        assert!(dashboard.check_login("admin", "secret"));
+        assert!(!dashboard.check_login("admin", "secre"));
+        assert!(!dashboard.check_login("root", "secret"));
+        assert!(!dashboard.check_login("", ""));
+    }
+
+    #[test]
+    fn expires_sessions() {
+        let dashboard = dashboard(false);
+        let id = dashboard.start_session();
+        assert!(dashboard.is_logged_in(&id));
+        assert!(!dashboard.is_logged_in("unknown"));
+
+        dashboard
+            .sessions()
+            .insert(id.clone(), Utc::now() - Duration::seconds(1));
+        assert!(!dashboard.is_logged_in(&id));
+        assert!(!dashboard.sessions().contains_key(&id));
+    }
+
+    #[test]
+    fn evicts_oldest_sessions() {
+        let dashboard = dashboard(false);
+        let now = Utc::now();
+        {
+            let mut sessions = dashboard.sessions();
+            sessions.insert("expired".to_owned(), now - Duration::days(1));
+            for i in 0..MAX_SESSIONS - 1 {
+                sessions.insert(format!("session-{i}"), now + Duration::hours(i as i64 + 1));
+            }
+        }
+
+        // The expired session makes room for the new one
+        let first = dashboard.start_session();
+        assert_eq!(dashboard.sessions().len(), MAX_SESSIONS);
+        assert!(!dashboard.sessions().contains_key("expired"));
+
+        let second = dashboard.start_session();
+        let sessions = dashboard.sessions();
+        assert_eq!(sessions.len(), MAX_SESSIONS);
+        assert!(!sessions.contains_key("session-0"));
+        assert!(sessions.contains_key("session-1"));
+        assert!(sessions.contains_key(&first) && sessions.contains_key(&second));
+    }
+
+    #[test]
+    fn throttles_failed_logins() {
+        let dashboard = dashboard(false);
+        let now = Utc::now();
+        for _ in 0..FREE_LOGIN_ATTEMPTS - 1 {
+            dashboard.login_failed(now);
+            assert_eq!(dashboard.login_delay(now), None);
+        }
+        dashboard.login_failed(now);
+        assert_eq!(dashboard.login_delay(now), Some(Duration::seconds(1)));
+        dashboard.login_failed(now);
+        assert_eq!(dashboard.login_delay(now), Some(Duration::seconds(2)));
+        assert_eq!(dashboard.login_delay(now + Duration::seconds(2)), None);
+
+        for _ in 0..40 {
+            dashboard.login_failed(now);
+        }
+        assert_eq!(dashboard.login_delay(now), Some(MAX_LOGIN_DELAY));
+
+        dashboard.login_succeeded();
+        assert_eq!(dashboard.login_delay(now), None);
+        dashboard.login_failed(now);
+        assert_eq!(dashboard.login_delay(now), None);
+    }
+
+    #[test]
+    fn parses_session_cookie() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(session_id(&headers), None);
+
+        headers.append(header::COOKIE, HeaderValue::from_static("theme=dark"));
+        assert_eq!(session_id(&headers), None);
+        headers.append(
+            header::COOKIE,
+            HeaderValue::from_static("lang=pl; easycar_session=abc123; other=1"),
+        );
+        assert_eq!(session_id(&headers), Some("abc123"));
+    }
+
+    #[test]
+    fn new_session_ids_are_random() {
+        let id = new_session_id();
+        assert_eq!(id.len(), 64);
+        assert!(id.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_ne!(id, new_session_id());
+    }
+
+    #[test]
+    fn secures_cookie_behind_tls() {
+        let mut headers = HeaderMap::new();
+        assert!(!cookie_attributes(&dashboard(false).login, &headers).contains("Secure"));
+        assert!(cookie_attributes(&dashboard(true).login, &headers).contains("Secure"));
+
+        headers.insert("x-forwarded-proto", HeaderValue::from_static("HTTPS"));
+        assert!(cookie_attributes(&dashboard(false).login, &headers).contains("Secure"));
+    }
+
+    #[test]
+    fn sums_places_per_day() {
+        let today = Utc::now().with_timezone(&Warsaw).date_naive();
+        let day = today + Duration::days(2);
+        let next = today + Duration::days(3);
+        let html = heatmap(
+            &[exam(day, 3), exam(day, 2), exam(next, 0)],
+            Duration::days(7),
+        );
+
+        assert_eq!(free_places(&html, today), Some("0"));
+        assert_eq!(free_places(&html, day), Some("5"));
+        // Exams without the number of places still count as one
+        assert_eq!(free_places(&html, next), Some("1"));
+    }
+
+    #[test]
+    fn heatmap_covers_horizon_and_exams() {
+        let today = Utc::now().with_timezone(&Warsaw).date_naive();
+        let horizon = Duration::days(30);
+        let html = heatmap(&[], horizon);
+        assert!(free_places(&html, today + horizon).is_some());
+        assert_eq!(free_places(&html, today + Duration::days(40)), None);
+
+        let far = today + Duration::days(60);
+        let html = heatmap(&[exam(far, 1)], horizon);
+        assert_eq!(free_places(&html, far), Some("1"));
+
+        // Weeks start on Monday
+        let rows = html.matches("<tr>").count() - 1;
+        assert_eq!(html.matches("<td").count(), rows * 7);
+
+        // An overflowing horizon is ignored
+        assert!(free_places(&heatmap(&[], Duration::MAX), today).is_some());
+    }
+}
@@ -0,0 +1,48 @@
+use std::{sync::OnceLock, time::Duration};
+
+use axum::{http::StatusCode, response::IntoResponse};
+use metrics_exporter_prometheus::{Matcher, PrometheusBuilder, PrometheusHandle};
+
+static HANDLE: OnceLock<PrometheusHandle> = OnceLock::new();
+
+/// Buckets of the request latency histogram, in seconds
+const LATENCY_BUCKETS: &[f64] = &[0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0];
+
+/// Starts collecting the metrics. Everything recorded before is lost.
+pub fn install() {
+    let recorder = PrometheusBuilder::new()
+        .set_buckets_for_metric(
+            Matcher::Full("info_car_request_duration_seconds".to_owned()),
+            LATENCY_BUCKETS,
+        )
+        .expect("The buckets are not empty")
+        .install_recorder();
+    let handle = match recorder {
+        Ok(handle) => handle,
+        Err(err) => {
+            log::error!("Failed to install the metrics recorder: {err}");
+            return;
+        }
+    };
+
+    // Drains the histograms, which would grow without bounds otherwise
+    let upkeep = handle.clone();
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(Duration::from_secs(5)).await;
+            upkeep.run_upkeep();
+        }
+    });
+    let _ = HANDLE.set(handle);
+}
+
+/// Renders the metrics in the Prometheus text format
+pub(super) async fn render() -> impl IntoResponse {
+    match HANDLE.get() {
+        Some(handle) => (StatusCode::OK, handle.render()),
+        None => (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "The metrics are not collected".to_owned(),
+        ),
+    }
+}
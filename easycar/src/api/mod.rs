@@ -0,0 +1,297 @@
+mod dashboard;
+#[cfg(feature = "prometheus")]
+pub mod metrics;
+
+use std::{collections::BTreeMap, sync::Arc};
+
+use axum::{
+    extract::{Path, Query, Request, State},
+    http::{header, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use info_car_api::error::{EnrollError, GenericClientError};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use subtle::ConstantTimeEq;
+use thiserror::Error;
+use tokio::net::TcpListener;
+use tokio_util::sync::CancellationToken;
+
+use dashboard::Dashboard;
+
+use crate::{
+    account::Account, client::GetExamsError, config::ApiConfig, service::reload::SharedConfig,
+};
+
+#[derive(Error, Debug)]
+pub enum ApiError {
+    #[error("Missing or invalid token")]
+    Unauthorized,
+    #[error("Unknown account {0}")]
+    UnknownAccount(String),
+    #[error(transparent)]
+    GetExamsError(#[from] GetExamsError),
+    #[error(transparent)]
+    GenericClientError(#[from] GenericClientError),
+    #[error(transparent)]
+    EnrollError(#[from] EnrollError),
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let status = match self {
+            ApiError::Unauthorized => StatusCode::UNAUTHORIZED,
+            ApiError::UnknownAccount(_) => StatusCode::NOT_FOUND,
+            // The errors of info-car.pl
+            _ => StatusCode::BAD_GATEWAY,
+        };
+        (status, Json(json!({ "error": self.to_string() }))).into_response()
+    }
+}
+
+#[derive(Clone)]
+struct ApiState {
+    accounts: Arc<Vec<Arc<Account>>>,
+    config: SharedConfig,
+    token: Arc<str>,
+    /// Not set if the dashboard is disabled
+    dashboard: Option<Dashboard>,
+}
+
+impl ApiState {
+    fn account(&self, name: &str) -> Result<&Arc<Account>, ApiError> {
+        self.accounts
+            .iter()
+            .find(|account| account.name == name)
+            .ok_or_else(|| ApiError::UnknownAccount(name.to_owned()))
+    }
+}
+
+/// Serves the HTTP API and the dashboard until the listener fails
+pub async fn serve(
+    api: ApiConfig,
+    accounts: Arc<Vec<Arc<Account>>>,
+    config: SharedConfig,
+    shutdown: CancellationToken,
+) -> std::io::Result<()> {
+    let state = ApiState {
+        accounts,
+        config,
+        token: api.token.into(),
+        dashboard: api.dashboard.map(Dashboard::new),
+    };
+    let listener = TcpListener::bind(api.bind).await?;
+    log::info!("Serving the HTTP API on {}", api.bind);
+    axum::serve(listener, router(state))
+        .with_graceful_shutdown(shutdown.cancelled_owned())
+        .await
+}
+
+fn router(state: ApiState) -> Router {
+    let api = Router::new()
+        .route("/health", get(health_details))
+        .route("/accounts", get(accounts))
+        .route("/accounts/{account}/exams", get(exams))
+        .route("/accounts/{account}/exams/{exam_id}/enroll", post(enroll))
+        .route("/accounts/{account}/words", get(words))
+        .route("/accounts/{account}/reservations", get(reservations))
+        .route(
+            "/accounts/{account}/reservations/{reservation_id}",
+            get(status).delete(cancel),
+        )
+        .route_layer(middleware::from_fn_with_state(state.clone(), authorize));
+
+    let mut router = Router::new()
+        .route("/health", get(health))
+        .nest("/api", api);
+    #[cfg(feature = "prometheus")]
+    {
+        router = router.merge(
+            Router::new()
+                .route("/metrics", get(metrics::render))
+                .route_layer(middleware::from_fn_with_state(state.clone(), authorize)),
+        );
+    }
+    if state.dashboard.is_some() {
+        router = router.merge(dashboard::router(state.clone()));
+    }
+    router.with_state(state)
+}
+
+/// Checks the `Authorization: Bearer <token>` header
+async fn authorize(
+    State(state): State<ApiState>,
+    request: Request,
+    next: Next,
+) -> Result<Response, ApiError> {
+    let token = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    if !token.is_some_and(|token| bool::from(token.as_bytes().ct_eq(state.token.as_bytes()))) {
+        return Err(ApiError::Unauthorized);
+    }
+    Ok(next.run(request).await)
+}
+
+fn health_status(healthy: bool) -> StatusCode {
+    if healthy {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    }
+}
+
+/// Responds with 503 if any account is unhealthy. It doesn't need the token, so the reports,
+/// which name the accounts and contain the errors, are only at `/api/health`.
+async fn health(State(state): State<ApiState>) -> (StatusCode, Json<Value>) {
+    let config = state.config.current();
+    let reports = state
+        .accounts
+        .iter()
+        .map(|account| account.health_report(&config))
+        .collect::<Vec<_>>();
+    let live = reports.iter().all(|report| report.live);
+    let healthy = reports.iter().all(|report| report.healthy);
+    (
+        health_status(healthy),
+        Json(json!({
+            "status": if healthy { "ok" } else { "unhealthy" },
+            "live": live,
+            "healthy": healthy,
+        })),
+    )
+}
+
+/// The health report of every account
+async fn health_details(State(state): State<ApiState>) -> (StatusCode, Json<Value>) {
+    let config = state.config.current();
+    let reports = state
+        .accounts
+        .iter()
+        .map(|account| (account.name.as_str(), account.health_report(&config)))
+        .collect::<BTreeMap<_, _>>();
+    let healthy = reports.values().all(|report| report.healthy);
+    (
+        health_status(healthy),
+        Json(json!({
+            "status": if healthy { "ok" } else { "unhealthy" },
+            "accounts": reports,
+        })),
+    )
+}
+
+async fn accounts(State(state): State<ApiState>) -> Json<Value> {
+    let mut accounts = Vec::new();
+    for account in state.accounts.iter() {
+        let client = account.client.lock().await;
+        accounts.push(json!({
+            "name": account.name,
+            "token_expires": client.get_token_expire_date(),
+            "criteria": client.criteria(),
+        }));
+    }
+    Json(Value::Array(accounts))
+}
+
+#[derive(Deserialize)]
+struct ExamsQuery {
+    #[serde(default = "default_exams_number")]
+    number: usize,
+}
+
+fn default_exams_number() -> usize {
+    5
+}
+
+async fn exams(
+    State(state): State<ApiState>,
+    Path(account): Path<String>,
+    Query(query): Query<ExamsQuery>,
+) -> Result<Json<Value>, ApiError> {
+    let account = state.account(&account)?;
+    let exams = match account
+        .client
+        .lock()
+        .await
+        .get_nearest_exams(query.number)
+        .await
+    {
+        Err(GetExamsError::NoExamsError) => Vec::new(),
+        result => result?,
+    };
+    Ok(Json(json!(exams)))
+}
+
+async fn enroll(
+    State(state): State<ApiState>,
+    Path((account, exam_id)): Path<(String, String)>,
+) -> Result<Json<Value>, ApiError> {
+    let account = state.account(&account)?;
+    let mut client = account.client.lock().await;
+    // Look the exam up first, so the reservation is made in the right WORD
+    if let Err(err) = client.get_nearest_exams(usize::MAX).await {
+        log::warn!("[{}] Failed to look up the exam: {err}", account.name);
+    }
+    let reservation_id = client.enroll(exam_id).await?;
+    drop(client);
+    log::info!("[{}] Enrolled with the API: {reservation_id}", account.name);
+    let deadline = account
+        .record_enrollment(&reservation_id, &state.config.current())
+        .await;
+    Ok(Json(json!({
+        "reservation_id": reservation_id,
+        "payment_deadline": deadline,
+    })))
+}
+
+async fn words(
+    State(state): State<ApiState>,
+    Path(account): Path<String>,
+) -> Result<Json<Value>, ApiError> {
+    let account = state.account(&account)?;
+    let words = account.client.lock().await.watched_words().await?;
+    Ok(Json(json!(words)))
+}
+
+async fn reservations(
+    State(state): State<ApiState>,
+    Path(account): Path<String>,
+) -> Result<Json<Value>, ApiError> {
+    let account = state.account(&account)?;
+    let reservations = account.client.lock().await.get_reservations().await?;
+    Ok(Json(json!(reservations)))
+}
+
+async fn status(
+    State(state): State<ApiState>,
+    Path((account, reservation_id)): Path<(String, String)>,
+) -> Result<Json<Value>, ApiError> {
+    let account = state.account(&account)?;
+    let status = account.client.lock().await.status(reservation_id).await?;
+    Ok(Json(json!(status)))
+}
+
+async fn cancel(
+    State(state): State<ApiState>,
+    Path((account, reservation_id)): Path<(String, String)>,
+) -> Result<Json<Value>, ApiError> {
+    let account = state.account(&account)?;
+    account
+        .client
+        .lock()
+        .await
+        .cancel(reservation_id.clone())
+        .await?;
+    log::info!(
+        "[{}] Cancelled with the API: {reservation_id}",
+        account.name
+    );
+    Ok(Json(
+        json!({ "reservation_id": reservation_id, "cancelled": true }),
+    ))
+}
@@ -0,0 +1,400 @@
+use std::{
+    io::{self, BufRead, Write},
+    path::PathBuf,
+    sync::Arc,
+};
+
+use chrono::{Duration, Utc};
+use clap::{Parser, Subcommand};
+use info_car_api::{
+    client::Client,
+    error::{EnrollError, GenericClientError, LoginError},
+};
+use serde::Serialize;
+use serde_json::json;
+use teloxide::Bot;
+use thiserror::Error;
+use tokio::time::sleep;
+
+use crate::{
+    account::Account,
+    client::GetExamsError,
+    config::{AccountConfig, Config, ConfigError},
+    service::NewServiceError,
+    utils::{plain_date, plain_date_from_string},
+    words::{render_words, WordsFormat},
+};
+
+#[derive(Parser, Debug)]
+#[command(version, about)]
+pub struct Cli {
+    /// Path to the configuration file
+    #[arg(short, long, global = true)]
+    pub config: Option<PathBuf>,
+    /// Name of the account to use. The first account is used by default.
+    #[arg(short, long, global = true)]
+    pub account: Option<String>,
+    /// Print the results as JSON
+    #[arg(long, global = true)]
+    pub json: bool,
+    /// Runs the telegram bot if not given
+    #[command(subcommand)]
+    pub command: Option<CliCommand>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum CliCommand {
+    /// Check whether the account can log in
+    LoginCheck,
+    /// List the closest exams which meet the watch criteria
+    Exams {
+        #[arg(short, long, default_value_t = 5)]
+        number: usize,
+    },
+    /// List the reservations
+    Reservations,
+    /// Show the status of a reservation
+    Status { reservation_id: String },
+    /// Enroll to an exam
+    Enroll { exam_id: String },
+    /// Cancel a reservation
+    Cancel { reservation_id: String },
+    /// Pay for a reservation with a BLIK code. The code is read from the standard input if not
+    /// given.
+    Pay {
+        reservation_id: String,
+        blik_code: Option<String>,
+    },
+    /// Search the WORD centers or print the mapping of all of them to their ids
+    Words {
+        /// Part of the name, address or province. All centers are printed if not given.
+        query: Option<String>,
+        /// Format of the mapping. `--json` implies the JSON format.
+        #[arg(short, long, value_enum, default_value_t)]
+        format: WordsFormat,
+        /// Write to a file instead of the standard output
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Watch for new exams and print them until interrupted
+    Watch,
+}
+
+#[derive(Error, Debug)]
+pub enum CliError {
+    #[error(transparent)]
+    ConfigError(#[from] ConfigError),
+    #[error("Unknown account '{0}'")]
+    UnknownAccount(String),
+    #[error(transparent)]
+    NewServiceError(#[from] NewServiceError),
+    #[error(transparent)]
+    LoginError(#[from] LoginError),
+    #[error(transparent)]
+    GenericClientError(#[from] GenericClientError),
+    #[error(transparent)]
+    EnrollError(#[from] EnrollError),
+    #[error(transparent)]
+    GetExamsError(#[from] GetExamsError),
+    #[error(transparent)]
+    JsonError(#[from] serde_json::Error),
+    #[error(transparent)]
+    IoError(#[from] io::Error),
+}
+
+impl Cli {
+    /// Loads the configuration from `--config` or the default sources. Returns the configuration
+    /// file if there is one.
+    pub fn load_config(
+        &self,
+        get: impl Fn(&str) -> Option<String>,
+    ) -> Result<(Config, Option<PathBuf>), ConfigError> {
+        match &self.config {
+            Some(path) => Ok((Config::load(path)?, Some(path.clone()))),
+            None => Ok((Config::discover(&get)?, Config::discover_file(&get))),
+        }
+    }
+
+    /// Runs the subcommand. Returns `false` if there is none and the bot should be started.
+    pub async fn run(&self, config: &Config) -> Result<bool, CliError> {
+        let Some(command) = &self.command else {
+            return Ok(false);
+        };
+        let account_config = match &self.account {
+            Some(name) => config
+                .account(name)
+                .ok_or_else(|| CliError::UnknownAccount(name.clone()))?,
+            None => &config.accounts[0],
+        };
+
+        if let CliCommand::Words {
+            query: None,
+            format,
+            output,
+        } = command
+        {
+            let format = if self.json {
+                WordsFormat::Json
+            } else {
+                *format
+            };
+            let client = login(account_config).await?;
+            let mapping = render_words(&client.word_centers().await?, format)?;
+            match output {
+                Some(path) => std::fs::write(path, mapping)?,
+                None => print!("{mapping}"),
+            }
+            return Ok(true);
+        }
+
+        let bot = config
+            .notifiers
+            .telegram
+            .as_ref()
+            .map(|telegram| Arc::new(Bot::new(&telegram.token)));
+        let account = Account::new(config, account_config, bot).await?;
+        let mut client = account.client.lock().await;
+        let output = Output { json: self.json };
+
+        match command {
+            CliCommand::LoginCheck => {
+                let expires = client.get_token_expire_date();
+                output.print(
+                    &json!({ "account": account.name, "token_expires": expires }),
+                    || {
+                        format!(
+                            "Logged in as {}. The token expires on {}",
+                            account.name,
+                            expires.map(plain_date).unwrap_or("unknown date".to_owned())
+                        )
+                    },
+                )?;
+            }
+            CliCommand::Exams { number } => {
+                let exams = match client.get_nearest_exams(*number).await {
+                    Err(GetExamsError::NoExamsError) => Vec::new(),
+                    result => result?,
+                };
+                output.print(&exams, || {
+                    if exams.is_empty() {
+                        return "No exams meet the criteria".to_owned();
+                    }
+                    exams
+                        .iter()
+                        .map(|found| {
+                            format!(
+                                "{}  {} {} in WORD {}, {} places, {} PLN (ID: {})",
+                                plain_date_from_string(&found.exam.date),
+                                found.kind,
+                                found.category,
+                                found.word_id,
+                                found.exam.places,
+                                found.exam.amount,
+                                found.exam.id
+                            )
+                        })
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                })?;
+            }
+            CliCommand::Reservations => {
+                let reservations = client.get_reservations().await?;
+                output.print(&reservations, || {
+                    if reservations.items.is_empty() {
+                        return "There are no reservations".to_owned();
+                    }
+                    reservations
+                        .items
+                        .iter()
+                        .map(|item| {
+                            let exam = item.exam.practice.as_ref().or(item.exam.theory.as_ref());
+                            format!(
+                                "{}  {}  {} {}, {} (status: {})",
+                                item.id,
+                                exam.map(|exam| plain_date_from_string(&exam.date))
+                                    .unwrap_or("unknown date".to_owned()),
+                                item.exam.category,
+                                item.exam.organization_unit_name,
+                                item.exam.address,
+                                item.status.status
+                            )
+                        })
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                })?;
+            }
+            CliCommand::Status { reservation_id } => {
+                let status = client.status(reservation_id.clone()).await?;
+                output.print(&status, || {
+                    let exam = status
+                        .exam
+                        .pracitce
+                        .as_ref()
+                        .or(status.exam.theory.as_ref());
+                    format!(
+                        "Reservation {}\nStatus: {}{}\nExam: {} {}\nWORD: {}, {}",
+                        status.id,
+                        status.status.status,
+                        status
+                            .status
+                            .message
+                            .as_ref()
+                            .map(|message| format!(" ({message})"))
+                            .unwrap_or_default(),
+                        status.exam.category,
+                        exam.map(|exam| plain_date_from_string(&exam.date))
+                            .unwrap_or("unknown date".to_owned()),
+                        status.exam.organization_unit_name,
+                        status.exam.address
+                    )
+                })?;
+            }
+            CliCommand::Enroll { exam_id } => {
+                // Look the exam up first, so the reservation is made in the right WORD
+                if let Err(err) = client.get_nearest_exams(usize::MAX).await {
+                    log::warn!("Failed to look up the exam: {err}");
+                }
+                let reservation_id = client.enroll(exam_id.clone()).await?;
+                // Saved for the reminders and the status watcher of the daemon
+                let deadline = account.record_enrollment(&reservation_id, config).await;
+                output.print(
+                    &json!({ "reservation_id": reservation_id, "payment_deadline": deadline }),
+                    || {
+                        format!(
+                            "Enrolled to exam {exam_id}. Reservation ID: {reservation_id}. Pay \
+                             until {}",
+                            plain_date(deadline)
+                        )
+                    },
+                )?;
+            }
+            CliCommand::Cancel { reservation_id } => {
+                client.cancel(reservation_id.clone()).await?;
+                output.print(
+                    &json!({ "reservation_id": reservation_id, "cancelled": true }),
+                    || format!("Cancelled reservation {reservation_id}"),
+                )?;
+            }
+            CliCommand::Pay {
+                reservation_id,
+                blik_code,
+            } => {
+                let blik_code = match blik_code {
+                    Some(code) => code.clone(),
+                    None => read_line("BLIK code: ")?,
+                };
+                let response = client.pay(reservation_id.clone(), blik_code).await?;
+                output.print(&response, || {
+                    format!(
+                        "Paid for reservation {reservation_id} with {:.2} PLN. Status: {}",
+                        response.paid_amount as f64 / 100.0,
+                        response.payment_status
+                    )
+                })?;
+            }
+            CliCommand::Words { query, .. } => {
+                let query = query.as_deref().unwrap_or_default();
+                let words = client.search_words(query, 10).await?;
+                output.print(&words, || {
+                    words
+                        .iter()
+                        .map(|info| {
+                            format!(
+                                "{:>4}  {}, {}{}{}",
+                                info.word.id,
+                                info.word.name,
+                                info.word.address,
+                                if info.word.offline { " (offline)" } else { "" },
+                                match info.reschedule_enabled {
+                                    Some(true) => " (rescheduling enabled)",
+                                    _ => "",
+                                }
+                            )
+                        })
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                })?;
+            }
+            CliCommand::Watch => {
+                drop(client);
+                watch(&account, config, output).await?;
+            }
+        }
+        Ok(true)
+    }
+}
+
+/// Prints either the JSON or the human readable form of the results
+#[derive(Clone, Copy)]
+struct Output {
+    json: bool,
+}
+
+impl Output {
+    fn print<T: Serialize>(self, value: &T, text: impl FnOnce() -> String) -> Result<(), CliError> {
+        if self.json {
+            println!("{}", serde_json::to_string(value)?);
+        } else {
+            println!("{}", text());
+        }
+        Ok(())
+    }
+}
+
+/// Prints every new closest exam. Runs until the process is interrupted.
+async fn watch(account: &Account, config: &Config, output: Output) -> Result<(), CliError> {
+    // The same margin as in the session worker, which isn't running here
+    let token_refresh_margin = Duration::minutes(5);
+    let mut last_exam_id = String::new();
+    loop {
+        let result = {
+            let mut client = account.client.lock().await;
+            if client
+                .get_token_expire_date()
+                .is_none_or(|expires| expires - token_refresh_margin < Utc::now())
+            {
+                log::info!("Refreshing the token...");
+                if let Err(err) = client.refresh_token().await {
+                    log::error!("Failed to refresh the token: {err}");
+                }
+            }
+            client.get_nearest_exams(1).await
+        };
+        match result {
+            Ok(mut exams) => {
+                let found = exams.pop().unwrap();
+                if found.exam.id != last_exam_id {
+                    last_exam_id = found.exam.id.clone();
+                    output.print(&found, || {
+                        format!(
+                            "{}  {} {} in WORD {} (ID: {})",
+                            plain_date_from_string(&found.exam.date),
+                            found.kind,
+                            found.category,
+                            found.word_id,
+                            found.exam.id
+                        )
+                    })?;
+                }
+            }
+            Err(GetExamsError::NoExamsError) => {}
+            Err(err) => log::error!("Got an error while retrieving new exams: {err}"),
+        }
+        sleep(config.poll_intervals.exams.to_std()).await;
+    }
+}
+
+fn read_line(prompt: &str) -> Result<String, io::Error> {
+    eprint!("{prompt}");
+    io::stderr().flush()?;
+    let mut line = String::new();
+    io::stdin().lock().read_line(&mut line)?;
+    Ok(line.trim().to_owned())
+}
+
+/// Logs in without fetching the user data
+async fn login(account: &AccountConfig) -> Result<Client, LoginError> {
+    let mut client = Client::new();
+    client.login(&account.username, &account.password).await?;
+    Ok(client)
+}
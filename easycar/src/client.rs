@@ -1,31 +1,72 @@
-use std::num::NonZeroU32;
+use std::{collections::HashMap, num::NonZeroU32};
 
-use chrono::{DateTime, Days, Utc};
+use chrono::{DateTime, Utc};
 use info_car_api::{
     client::Client,
-    error::{EnrollError, GenericClientError, LoginError},
+    error::{EnrollError, GenericClientError, LoginError, LogoutError, RefreshTokenError},
+    ical::{Calendar, CalendarEvent},
     types::*,
-    utils::find_n_practice_exams,
+    utils::{find_all_practice_exams, find_all_theory_exams, search_words},
 };
+use metrics::counter;
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
+use crate::{
+    config::{ExamKind, WatchConfig},
+    i18n::{Language, Text},
+    utils::try_date_from_string,
+};
+
 #[derive(Clone)]
 pub struct UserData {
     pub username: String,
     pub password: String,
-    pub preferred_osk: NonZeroU32,
 }
 
 impl UserData {
-    pub fn new(username: String, password: String, preferred_osk: NonZeroU32) -> Self {
-        UserData {
-            username,
-            password,
-            preferred_osk,
+    pub fn new(username: String, password: String) -> Self {
+        UserData { username, password }
+    }
+}
+
+/// An exam found by the scheduler together with the criteria it was found with
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct FoundExam {
+    pub exam: Exam,
+    pub word_id: NonZeroU32,
+    pub category: LicenseCategory,
+    pub kind: ExamKind,
+}
+
+/// A request made by a slow operation, reported to show its progress
+#[derive(Clone, Debug)]
+pub enum Step {
+    FetchingSchedule(NonZeroU32, LicenseCategory),
+    FetchingWordDetails,
+}
+
+impl Step {
+    pub fn describe(&self, language: Language) -> String {
+        match self {
+            Step::FetchingSchedule(word_id, category) => language.format(
+                Text::FetchingSchedule,
+                &[("word", word_id), ("category", category)],
+            ),
+            Step::FetchingWordDetails => language.text(Text::FetchingWordDetails).to_owned(),
         }
     }
 }
 
+/// A WORD center found by [`InfoCarClient::search_words`]
+#[derive(Clone, Debug, Serialize)]
+pub struct WordInfo {
+    pub word: Word,
+    pub province: Option<String>,
+    /// `None` if it couldn't be checked
+    pub reschedule_enabled: Option<bool>,
+}
+
 #[derive(Error, Debug)]
 pub enum GetExamsError {
     #[error(transparent)]
@@ -34,6 +75,14 @@ pub enum GetExamsError {
     NoExamsError,
 }
 
+#[derive(Error, Debug)]
+pub enum ActiveReservationError {
+    #[error(transparent)]
+    GenericClientError(#[from] GenericClientError),
+    #[error(transparent)]
+    StatusError(#[from] EnrollError),
+}
+
 #[derive(Error, Debug)]
 pub enum NewClientError {
     #[error(transparent)]
@@ -46,6 +95,9 @@ pub struct InfoCarClient {
     client: Client,
     user_data: UserData,
     candidate_data: NewReservationCandidate,
+    criteria: WatchConfig,
+    /// Upcoming exams returned by the searches, used to find out where to enroll
+    known_exams: HashMap<String, FoundExam>,
 }
 
 impl InfoCarClient {
@@ -54,6 +106,7 @@ impl InfoCarClient {
         pesel: String,
         phone_number: String,
         driver_profile_id: ProfileIdType,
+        criteria: WatchConfig,
     ) -> Result<Self, NewClientError> {
         let mut client = Client::new();
         client
@@ -72,59 +125,274 @@ impl InfoCarClient {
                 phone_number,
                 driver_profile_id,
             ),
+            criteria,
+            known_exams: HashMap::new(),
         })
     }
 
     pub async fn login(&mut self) -> Result<DateTime<Utc>, LoginError> {
-        self.client
-            .login(&self.user_data.username, &self.user_data.password)
-            .await?;
-        Ok(self
+        let result = self
             .client
-            .token_expire_date
-            .expect("Expire date is not available"))
+            .login(&self.user_data.username, &self.user_data.password)
+            .await;
+        let outcome = if result.is_ok() { "ok" } else { "error" };
+        counter!("easycar_logins_total", "result" => outcome).increment(1);
+        result?;
+        self.token_expire_date()
     }
 
     pub async fn refresh_token(&mut self) -> Result<DateTime<Utc>, LoginError> {
         if let Err(err) = self.client.refresh_token().await {
             log::warn!("Refreshing token returned an error: {err}. Relogging");
+            counter!("easycar_token_refreshes_total", "result" => "error").increment(1);
             self.login().await
         } else {
-            Ok(self
-                .client
-                .token_expire_date
-                .expect("Expire date is not set"))
+            counter!("easycar_token_refreshes_total", "result" => "ok").increment(1);
+            self.token_expire_date()
         }
     }
 
+    pub async fn logout(&mut self) -> Result<(), LogoutError> {
+        self.client.logout().await
+    }
+
+    fn token_expire_date(&self) -> Result<DateTime<Utc>, LoginError> {
+        self.client
+            .token_expire_date
+            .ok_or(RefreshTokenError::ExpireTimeNotProvided.into())
+    }
+
     pub fn get_token_expire_date(&self) -> Option<DateTime<Utc>> {
         self.client.token_expire_date
     }
 
-    pub async fn get_nearest_exams(&mut self, number: usize) -> Result<Vec<Exam>, GetExamsError> {
-        let schedule = self
+    pub fn criteria(&self) -> &WatchConfig {
+        &self.criteria
+    }
+
+    pub fn set_criteria(&mut self, criteria: WatchConfig) {
+        self.criteria = criteria;
+    }
+
+    /// Fetches the schedules of every watched WORD and category, describing each request with
+    /// `on_step`
+    pub async fn get_schedules(
+        &mut self,
+        on_step: &(dyn Fn(Step) + Send + Sync),
+    ) -> Result<Vec<ExamSchedule>, EnrollError> {
+        let now = Utc::now();
+        let end = now
+            .checked_add_signed(self.criteria.horizon.0)
+            .unwrap_or(DateTime::<Utc>::MAX_UTC);
+        let mut schedules = Vec::new();
+        for word_id in &self.criteria.words {
+            for category in &self.criteria.categories {
+                on_step(Step::FetchingSchedule(*word_id, category.clone()));
+                schedules.push(
+                    self.client
+                        .exam_schedule(*word_id, now, end, category.clone())
+                        .await?,
+                );
+            }
+        }
+        Ok(schedules)
+    }
+
+    /// Returns up to `number` closest exams which meet the watch criteria
+    pub async fn get_nearest_exams(
+        &mut self,
+        number: usize,
+    ) -> Result<Vec<FoundExam>, GetExamsError> {
+        self.get_nearest_exams_with_steps(number, &|_| {}).await
+    }
+
+    /// Same as [`Self::get_nearest_exams`], describing every request with `on_step`
+    pub async fn get_nearest_exams_with_steps(
+        &mut self,
+        number: usize,
+        on_step: &(dyn Fn(Step) + Send + Sync),
+    ) -> Result<Vec<FoundExam>, GetExamsError> {
+        let schedules = self.get_schedules(on_step).await?;
+
+        let mut exams: Vec<FoundExam> = schedules
+            .iter()
+            .flat_map(|schedule| {
+                self.criteria.exam_kinds.iter().flat_map(move |kind| {
+                    let exams = match kind {
+                        ExamKind::Practice => find_all_practice_exams(schedule),
+                        ExamKind::Theory => find_all_theory_exams(schedule),
+                    };
+                    exams.into_iter().map(move |exam| FoundExam {
+                        exam: exam.clone(),
+                        word_id: schedule.organization_id,
+                        category: schedule.category.clone(),
+                        kind: *kind,
+                    })
+                })
+            })
+            .filter(|found| self.criteria.filter.matches(&found.exam))
+            .collect();
+        // Dates are in the ISO format so they can be compared as strings
+        exams.sort_by(|a, b| a.exam.date.cmp(&b.exam.date));
+        exams.truncate(number);
+
+        // The past exams can't be enrolled to anymore, so they are dropped to keep the map bounded
+        let now = Utc::now();
+        self.known_exams.retain(|_, found| {
+            try_date_from_string(&found.exam.date).is_some_and(|date| date > now)
+        });
+        self.known_exams.extend(
+            exams
+                .iter()
+                .map(|found| (found.exam.id.clone(), found.clone())),
+        );
+
+        (!exams.is_empty())
+            .then_some(exams)
+            .ok_or(GetExamsError::NoExamsError)
+    }
+
+    /// Returns a calendar with all free exam slots in the watched WORDs, describing every request
+    /// with `on_step`
+    pub async fn exams_calendar(
+        &mut self,
+        on_step: &(dyn Fn(Step) + Send + Sync),
+    ) -> Result<Calendar, EnrollError> {
+        let schedules = self.get_schedules(on_step).await?;
+        on_step(Step::FetchingWordDetails);
+        let words = self
             .client
-            .exam_schedule(
-                self.user_data.preferred_osk,
-                Utc::now(),
-                Utc::now().checked_add_days(Days::new(31)).unwrap(),
-                LicenseCategory::B,
-            )
-            .await?;
+            .word_centers()
+            .await
+            .inspect_err(|err| log::warn!("Failed to get the WORD details: {err}"))
+            .map(|centers| centers.words)
+            .unwrap_or_default();
 
-        find_n_practice_exams(schedule, number).ok_or(GetExamsError::NoExamsError)
+        Ok(schedules
+            .iter()
+            .flat_map(|schedule| {
+                let word = words
+                    .iter()
+                    .find(|word| word.id == schedule.organization_id);
+                self.criteria.exam_kinds.iter().flat_map(move |kind| {
+                    let (exams, event): (_, fn(_, _) -> _) = match kind {
+                        ExamKind::Practice => (
+                            find_all_practice_exams(schedule),
+                            CalendarEvent::from_practice_exam,
+                        ),
+                        ExamKind::Theory => (
+                            find_all_theory_exams(schedule),
+                            CalendarEvent::from_theory_exam,
+                        ),
+                    };
+                    exams.into_iter().filter_map(move |exam| event(exam, word))
+                })
+            })
+            .collect())
+    }
+
+    /// Returns a calendar with all active reservations
+    pub async fn reservations_calendar(&mut self) -> Result<Calendar, ActiveReservationError> {
+        let reservations = self.client.my_reservations().await?;
+        let mut calendar = Calendar::new();
+        for item in reservations
+            .items
+            .into_iter()
+            .filter(|item| item.status.status.is_active())
+        {
+            let status = self.client.reservation_status(item.id).await?;
+            calendar
+                .events
+                .extend(CalendarEvent::from_reservation_status(&status));
+        }
+        Ok(calendar)
+    }
+
+    /// Returns a calendar with every reservation from the list, including the cancelled ones
+    pub async fn reservation_list_calendar(&mut self) -> Result<Calendar, GenericClientError> {
+        let reservations = self.client.my_reservations().await?;
+        Ok(reservations
+            .items
+            .iter()
+            .flat_map(CalendarEvent::from_reservation)
+            .collect())
+    }
+
+    /// Returns up to `number` WORD centers best matching the query
+    pub async fn search_words(
+        &mut self,
+        query: &str,
+        number: usize,
+    ) -> Result<Vec<WordInfo>, GenericClientError> {
+        let centers = self.client.word_centers().await?;
+        let mut words = Vec::new();
+        for (word, province) in search_words(&centers, query).into_iter().take(number) {
+            let reschedule_enabled = self
+                .client
+                .is_word_reschedule_enabled(word.id)
+                .await
+                .inspect_err(|err| log::warn!("Failed to check WORD {}: {err}", word.id))
+                .ok();
+            words.push(WordInfo {
+                word: word.clone(),
+                province: province.map(|province| province.name.clone()),
+                reschedule_enabled,
+            });
+        }
+        Ok(words)
+    }
+
+    /// Returns the details of the watched WORD centers
+    pub async fn watched_words(&self) -> Result<Vec<Word>, GenericClientError> {
+        let centers = self.client.word_centers().await?;
+        Ok(centers
+            .words
+            .into_iter()
+            .filter(|word| self.criteria.words.contains(&word.id))
+            .collect())
     }
 
     pub async fn get_reservations(&mut self) -> Result<ReservationList, GenericClientError> {
         self.client.my_reservations().await
     }
 
+    /// Returns the status of the first reservation which holds a place on an exam
+    pub async fn get_active_reservation(
+        &mut self,
+    ) -> Result<Option<ReservationStatus>, ActiveReservationError> {
+        let reservations = self.client.my_reservations().await?;
+        let Some(active) = reservations
+            .items
+            .into_iter()
+            .find(|item| item.status.status.is_active())
+        else {
+            return Ok(None);
+        };
+
+        Ok(Some(self.client.reservation_status(active.id).await?))
+    }
+
     pub async fn enroll(&mut self, exam_id: String) -> Result<String, EnrollError> {
-        let reservation = NewReservation::new(
-            self.candidate_data.clone(),
-            NewReservationExam::new_practice_exam(self.user_data.preferred_osk, exam_id),
-            ReservationLanguageAndOsk::default(),
-        );
+        // Exams which weren't seen by the scheduler are assumed to match the first criteria
+        let (word_id, category, kind) = match self.known_exams.get(&exam_id) {
+            Some(found) => (found.word_id, found.category.clone(), found.kind),
+            None => (
+                self.criteria.words[0],
+                self.criteria.categories[0].clone(),
+                self.criteria.exam_kinds[0],
+            ),
+        };
+        let exam = match kind {
+            ExamKind::Practice => NewReservationExam::new_practice_exam(word_id, exam_id),
+            ExamKind::Theory => NewReservationExam::new_theory_exam(word_id, exam_id),
+        };
+        let candidate = NewReservationCandidate {
+            category,
+            ..self.candidate_data.clone()
+        };
+
+        let reservation =
+            NewReservation::new(candidate, exam, ReservationLanguageAndOsk::default());
 
         self.client.new_reservation(reservation).await
     }
@@ -0,0 +1,1581 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt, fs, io,
+    net::{Ipv4Addr, SocketAddr},
+    num::NonZeroU32,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use chrono::{Datelike, Duration, NaiveDate, NaiveTime, Weekday};
+use info_car_api::types::{Exam, LicenseCategory, ProfileIdType};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use thiserror::Error;
+
+use crate::{
+    i18n::{fill, placeholders, Language, Text},
+    notifier::Event,
+    utils::{parse_duration, short_duration, try_date_from_string},
+};
+
+/// Default path of the configuration file
+pub const CONFIG_FILE: &str = "Secrets.toml";
+
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("Failed to read the configuration file {0}: {1}")]
+    IoError(PathBuf, io::Error),
+    #[error("Failed to parse the configuration: {0}")]
+    ParseError(#[from] toml::de::Error),
+    #[error("Missing legacy configuration key: {0}")]
+    MissingKey(&'static str),
+    #[error("Invalid value of {0}: {1}")]
+    InvalidValue(String, String),
+}
+
+/// A duration written in a short form, such as `15s`, `30m`, `2h` or `7d`
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub struct HumanDuration(pub Duration);
+
+impl HumanDuration {
+    pub fn to_std(self) -> std::time::Duration {
+        self.0.to_std().unwrap_or_default()
+    }
+}
+
+impl FromStr for HumanDuration {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_duration(s).map(HumanDuration).ok_or(format!(
+            "invalid duration '{s}', use e.g. 15s, 30m, 2h or 7d"
+        ))
+    }
+}
+
+impl fmt::Display for HumanDuration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", short_duration(self.0))
+    }
+}
+
+impl<'de> Deserialize<'de> for HumanDuration {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+impl Serialize for HumanDuration {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExamKind {
+    Theory,
+    Practice,
+}
+
+impl fmt::Display for ExamKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExamKind::Theory => write!(f, "theory"),
+            ExamKind::Practice => write!(f, "practice"),
+        }
+    }
+}
+
+/// Criteria which the exams have to meet to be reported
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExamFilter {
+    /// The earliest acceptable exam day
+    pub not_before: Option<NaiveDate>,
+    /// The latest acceptable exam day
+    pub not_after: Option<NaiveDate>,
+    /// Acceptable days of the week. All days are accepted if empty.
+    #[serde(default)]
+    pub weekdays: Vec<Weekday>,
+    /// The earliest acceptable exam hour
+    pub earliest_time: Option<NaiveTime>,
+    /// The latest acceptable exam hour
+    pub latest_time: Option<NaiveTime>,
+    /// Minimal number of free places
+    pub min_places: Option<i32>,
+    /// Maximal exam price in PLN
+    pub max_price: Option<i32>,
+}
+
+impl ExamFilter {
+    pub fn matches(&self, exam: &Exam) -> bool {
+        let Some(date) = try_date_from_string(&exam.date) else {
+            return false;
+        };
+        let date = date.with_timezone(&chrono_tz::Europe::Warsaw).naive_local();
+
+        self.not_before.is_none_or(|day| date.date() >= day)
+            && self.not_after.is_none_or(|day| date.date() <= day)
+            && (self.weekdays.is_empty() || self.weekdays.contains(&date.weekday()))
+            && self.earliest_time.is_none_or(|time| date.time() >= time)
+            && self.latest_time.is_none_or(|time| date.time() <= time)
+            && self.min_places.is_none_or(|places| exam.places >= places)
+            && self.max_price.is_none_or(|price| exam.amount <= price)
+    }
+}
+
+impl fmt::Display for ExamFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut conditions = Vec::new();
+        if let Some(day) = self.not_before {
+            conditions.push(format!("not_before {day}"));
+        }
+        if let Some(day) = self.not_after {
+            conditions.push(format!("not_after {day}"));
+        }
+        if !self.weekdays.is_empty() {
+            let weekdays: Vec<String> = self.weekdays.iter().map(|day| day.to_string()).collect();
+            conditions.push(format!("weekdays {}", weekdays.join(" ")));
+        }
+        if let Some(time) = self.earliest_time {
+            conditions.push(format!("earliest_time {}", time.format("%H:%M")));
+        }
+        if let Some(time) = self.latest_time {
+            conditions.push(format!("latest_time {}", time.format("%H:%M")));
+        }
+        if let Some(places) = self.min_places {
+            conditions.push(format!("min_places {places}"));
+        }
+        if let Some(price) = self.max_price {
+            conditions.push(format!("max_price {price}"));
+        }
+
+        if conditions.is_empty() {
+            write!(f, "none")
+        } else {
+            write!(f, "{}", conditions.join(", "))
+        }
+    }
+}
+
+fn default_categories() -> Vec<LicenseCategory> {
+    vec![LicenseCategory::B]
+}
+
+fn default_exam_kinds() -> Vec<ExamKind> {
+    vec![ExamKind::Practice]
+}
+
+fn default_horizon() -> HumanDuration {
+    HumanDuration(Duration::days(31))
+}
+
+/// The longest horizon, the exams aren't scheduled further ahead anyway
+const MAX_HORIZON: Duration = Duration::days(366);
+
+/// What the scheduler looks for
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WatchConfig {
+    /// Ids of the WORD centers (see `docs/WORD-mapping.md`)
+    pub words: Vec<NonZeroU32>,
+    #[serde(default = "default_categories")]
+    pub categories: Vec<LicenseCategory>,
+    #[serde(default = "default_exam_kinds")]
+    pub exam_kinds: Vec<ExamKind>,
+    /// How far into the future to look for exams
+    #[serde(default = "default_horizon")]
+    pub horizon: HumanDuration,
+    #[serde(default)]
+    pub filter: ExamFilter,
+}
+
+impl WatchConfig {
+    pub fn new(word: NonZeroU32) -> Self {
+        Self {
+            words: vec![word],
+            categories: default_categories(),
+            exam_kinds: default_exam_kinds(),
+            horizon: default_horizon(),
+            filter: ExamFilter::default(),
+        }
+    }
+
+    pub fn validate(&self, context: &str) -> Result<(), ConfigError> {
+        let invalid = |field: &str, reason: &str| {
+            Err(ConfigError::InvalidValue(
+                format!("{context}.{field}"),
+                reason.to_owned(),
+            ))
+        };
+        if self.words.is_empty() {
+            return invalid("words", "at least one WORD id is required");
+        }
+        if self.categories.is_empty() {
+            return invalid("categories", "at least one category is required");
+        }
+        if self.exam_kinds.is_empty() {
+            return invalid("exam_kinds", "at least one exam kind is required");
+        }
+        if self.horizon.0 <= Duration::zero() {
+            return invalid("horizon", "has to be positive");
+        }
+        if self.horizon.0 > MAX_HORIZON {
+            return invalid("horizon", "can't be longer than a year");
+        }
+        if let (Some(start), Some(end)) = (self.filter.not_before, self.filter.not_after) {
+            if start > end {
+                return invalid("filter", "not_before is after not_after");
+            }
+        }
+        if let (Some(start), Some(end)) = (self.filter.earliest_time, self.filter.latest_time) {
+            if start > end {
+                return invalid("filter", "earliest_time is after latest_time");
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AccountConfig {
+    /// A unique name used in the logs and in the state file name
+    pub name: String,
+    pub username: String,
+    pub password: String,
+    pub pesel: String,
+    pub phone_number: String,
+    /// Candidate (PKK) or professional driver (PKZ) profile number
+    #[serde(flatten)]
+    pub driver_profile: ProfileIdType,
+    /// Telegram chat which receives the notifications and may control this account
+    pub chat_id: Option<i64>,
+    /// Language of the messages, which can be changed from the chat
+    #[serde(default)]
+    pub language: Language,
+    /// Overrides the global watch criteria
+    pub watch: Option<WatchConfig>,
+    /// Keeps an iCalendar file with the active reservations up to date
+    pub calendar_file: Option<PathBuf>,
+    /// Where to send the notifications. Everything goes to the telegram chat if empty, or to the
+    /// standard output without telegram.
+    #[serde(default)]
+    pub notify: Vec<NotifyRoute>,
+}
+
+/// Sends the chosen events to a notifier
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NotifyRoute {
+    /// `telegram`, `stdout` or `<kind>.<name>` of a notifier from the `[notifiers]` table, e.g.
+    /// `ntfy.phone`
+    pub notifier: String,
+    /// All events are sent if empty
+    #[serde(default)]
+    pub events: Vec<Event>,
+}
+
+impl NotifyRoute {
+    pub fn new(notifier: &str) -> Self {
+        Self {
+            notifier: notifier.to_owned(),
+            events: Vec::new(),
+        }
+    }
+
+    pub fn accepts(&self, event: Event) -> bool {
+        self.events.is_empty() || self.events.contains(&event)
+    }
+}
+
+fn default_exams_interval() -> HumanDuration {
+    HumanDuration(Duration::seconds(15))
+}
+
+fn default_reservations_interval() -> HumanDuration {
+    HumanDuration(Duration::seconds(60))
+}
+
+fn default_payments_interval() -> HumanDuration {
+    HumanDuration(Duration::seconds(30))
+}
+
+fn default_reminders_interval() -> HumanDuration {
+    HumanDuration(Duration::minutes(5))
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PollIntervals {
+    /// Checking for new exams
+    #[serde(default = "default_exams_interval")]
+    pub exams: HumanDuration,
+    /// Checking for reservation status changes
+    #[serde(default = "default_reservations_interval")]
+    pub reservations: HumanDuration,
+    /// Checking for unpaid reservations
+    #[serde(default = "default_payments_interval")]
+    pub payments: HumanDuration,
+    /// Checking for upcoming exams
+    #[serde(default = "default_reminders_interval")]
+    pub reminders: HumanDuration,
+}
+
+impl Default for PollIntervals {
+    fn default() -> Self {
+        Self {
+            exams: default_exams_interval(),
+            reservations: default_reservations_interval(),
+            payments: default_payments_interval(),
+            reminders: default_reminders_interval(),
+        }
+    }
+}
+
+fn default_payment_window() -> HumanDuration {
+    HumanDuration(Duration::hours(1))
+}
+
+fn default_payment_reminders() -> Vec<HumanDuration> {
+    [30, 10, 3]
+        .map(|minutes| HumanDuration(Duration::minutes(minutes)))
+        .to_vec()
+}
+
+fn default_exam_reminders() -> Vec<HumanDuration> {
+    vec![
+        HumanDuration(Duration::days(7)),
+        HumanDuration(Duration::days(1)),
+        HumanDuration(Duration::hours(2)),
+    ]
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RemindersConfig {
+    /// Time given for paying for a new reservation
+    #[serde(default = "default_payment_window")]
+    pub payment_window: HumanDuration,
+    /// When to remind about the payment before the deadline
+    #[serde(default = "default_payment_reminders")]
+    pub payment: Vec<HumanDuration>,
+    /// When to remind about the exam. Can be changed per user with `/reminders`.
+    #[serde(default = "default_exam_reminders")]
+    pub exam: Vec<HumanDuration>,
+}
+
+impl RemindersConfig {
+    /// Returns the default exam reminder offsets in minutes
+    pub fn exam_offsets(&self) -> Vec<i64> {
+        self.exam
+            .iter()
+            .map(|offset| offset.0.num_minutes())
+            .collect()
+    }
+}
+
+impl Default for RemindersConfig {
+    fn default() -> Self {
+        Self {
+            payment_window: default_payment_window(),
+            payment: default_payment_reminders(),
+            exam: default_exam_reminders(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TelegramConfig {
+    pub token: String,
+    /// Receives the reports about crashed workers
+    pub admin_chat_id: Option<i64>,
+    /// Receives the updates with a webhook instead of long polling
+    pub webhook: Option<TelegramWebhookConfig>,
+}
+
+fn default_webhook_bind() -> SocketAddr {
+    (Ipv4Addr::LOCALHOST, 8443).into()
+}
+
+/// The webhook listener, only used when built with the `webhook` feature
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TelegramWebhookConfig {
+    /// The public URL telegram sends the updates to, forwarded to `bind`
+    pub url: String,
+    /// Replaced with the address given by shuttle
+    #[serde(default = "default_webhook_bind")]
+    pub bind: SocketAddr,
+    /// Sent by telegram in the `X-Telegram-Bot-Api-Secret-Token` header. Generated if not set.
+    pub secret_token: Option<String>,
+}
+
+/// Posts the notifications as JSON
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WebhookConfig {
+    pub url: String,
+    /// Additional headers, e.g. `Authorization`
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+}
+
+/// Sends the notifications by email
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EmailConfig {
+    /// SMTP server
+    pub server: String,
+    /// Defaults to 465, or 587 with STARTTLS
+    pub port: Option<u16>,
+    /// Use STARTTLS instead of implicit TLS
+    #[serde(default)]
+    pub starttls: bool,
+    pub username: String,
+    pub password: String,
+    pub from: String,
+    pub to: Vec<String>,
+}
+
+fn default_ntfy_server() -> String {
+    "https://ntfy.sh".to_owned()
+}
+
+/// Publishes the notifications to an ntfy topic
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NtfyConfig {
+    #[serde(default = "default_ntfy_server")]
+    pub server: String,
+    pub topic: String,
+    /// Access token of a protected topic
+    pub token: Option<String>,
+}
+
+/// Sends the notifications to a Matrix room
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MatrixConfig {
+    /// e.g. `https://matrix.org`
+    pub homeserver: String,
+    pub access_token: String,
+    /// e.g. `!abcdef:matrix.org`
+    pub room_id: String,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    #[default]
+    Text,
+    /// One JSON object per line
+    Json,
+}
+
+/// Writes the notifications to the standard output or appends them to a file
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LogConfig {
+    #[serde(default)]
+    pub format: LogFormat,
+    /// The standard output is used if not given
+    pub path: Option<PathBuf>,
+}
+
+/// Runs a program for every notification. The details are passed in the `EASYCAR_*` environment
+/// variables.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CommandConfig {
+    pub program: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NotifiersConfig {
+    /// The bot isn't started without it
+    pub telegram: Option<TelegramConfig>,
+    #[serde(default)]
+    pub webhook: BTreeMap<String, WebhookConfig>,
+    #[serde(default)]
+    pub email: BTreeMap<String, EmailConfig>,
+    #[serde(default)]
+    pub ntfy: BTreeMap<String, NtfyConfig>,
+    #[serde(default)]
+    pub matrix: BTreeMap<String, MatrixConfig>,
+    #[serde(default)]
+    pub command: BTreeMap<String, CommandConfig>,
+    #[serde(default)]
+    pub log: BTreeMap<String, LogConfig>,
+}
+
+impl NotifiersConfig {
+    pub fn new(telegram: Option<TelegramConfig>) -> Self {
+        Self {
+            telegram,
+            ..Default::default()
+        }
+    }
+
+    /// Returns the names of all configured notifiers, as used in [`NotifyRoute::notifier`]
+    pub fn names(&self) -> Vec<String> {
+        let names = |kind: &str, keys: Vec<&String>| {
+            keys.into_iter()
+                .map(|name| format!("{kind}.{name}"))
+                .collect::<Vec<_>>()
+        };
+        let builtin = match self.telegram {
+            Some(_) => vec!["telegram".to_owned(), "stdout".to_owned()],
+            None => vec!["stdout".to_owned()],
+        };
+        [
+            builtin,
+            names("webhook", self.webhook.keys().collect()),
+            names("email", self.email.keys().collect()),
+            names("ntfy", self.ntfy.keys().collect()),
+            names("matrix", self.matrix.keys().collect()),
+            names("command", self.command.keys().collect()),
+            names("log", self.log.keys().collect()),
+        ]
+        .concat()
+    }
+}
+
+fn default_api_bind() -> SocketAddr {
+    (Ipv4Addr::LOCALHOST, 8080).into()
+}
+
+/// The HTTP API, only served when built with the `api` feature
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ApiConfig {
+    #[serde(default = "default_api_bind")]
+    pub bind: SocketAddr,
+    /// Required in the `Authorization: Bearer <token>` header
+    pub token: String,
+    /// Serves the web dashboard if set
+    pub dashboard: Option<DashboardConfig>,
+}
+
+/// Login to the web dashboard
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DashboardConfig {
+    pub username: String,
+    pub password: String,
+    /// Sends the session cookie only over HTTPS. It's also done for the requests forwarded by a
+    /// TLS proxy with `X-Forwarded-Proto: https`.
+    #[serde(default)]
+    pub secure_cookie: bool,
+}
+
+fn default_state_dir() -> PathBuf {
+    PathBuf::from(".")
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StorageConfig {
+    /// Directory with the state files of the accounts
+    #[serde(default = "default_state_dir")]
+    pub state_dir: PathBuf,
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        Self {
+            state_dir: default_state_dir(),
+        }
+    }
+}
+
+impl StorageConfig {
+    pub fn state_file(&self, account: &str) -> PathBuf {
+        self.state_dir.join(format!("easycar-{account}.json"))
+    }
+}
+
+fn default_shutdown_timeout() -> HumanDuration {
+    HumanDuration(Duration::seconds(10))
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ShutdownConfig {
+    /// How long to wait for the workers, the state and the notifications before exiting anyway
+    #[serde(default = "default_shutdown_timeout")]
+    pub timeout: HumanDuration,
+    /// Log out of info-car.pl, ending the session
+    #[serde(default)]
+    pub logout: bool,
+}
+
+impl Default for ShutdownConfig {
+    fn default() -> Self {
+        Self {
+            timeout: default_shutdown_timeout(),
+            logout: false,
+        }
+    }
+}
+
+/// A custom message, either for every language or separately for each of them
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Template {
+    All(String),
+    Localized(LocalizedTemplate),
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LocalizedTemplate {
+    pub en: Option<String>,
+    pub pl: Option<String>,
+}
+
+impl Template {
+    /// The template in the `language`, `None` means the built-in message
+    pub fn get(&self, language: Language) -> Option<&str> {
+        match (self, language) {
+            (Template::All(template), _) => Some(template),
+            (Template::Localized(templates), Language::English) => templates.en.as_deref(),
+            (Template::Localized(templates), Language::Polish) => templates.pl.as_deref(),
+        }
+    }
+
+    fn all(&self) -> impl Iterator<Item = &str> {
+        match self {
+            Template::All(template) => vec![template.as_str()],
+            Template::Localized(templates) => [&templates.en, &templates.pl]
+                .into_iter()
+                .flatten()
+                .map(String::as_str)
+                .collect(),
+        }
+        .into_iter()
+    }
+}
+
+/// Custom wording of the messages, in the telegram flavoured HTML. The `{field}`s are replaced
+/// with the values.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TemplatesConfig {
+    pub new_exam: Option<Template>,
+    pub enrolled: Option<Template>,
+    pub status_change: Option<Template>,
+    pub error: Option<Template>,
+    pub exam_reminder: Option<Template>,
+    pub payment_reminder: Option<Template>,
+}
+
+impl TemplatesConfig {
+    /// The name, the built-in text and the fields of every template
+    fn templates(
+        &self,
+    ) -> [(
+        &'static str,
+        Text,
+        &'static [&'static str],
+        Option<&Template>,
+    ); 6] {
+        [
+            (
+                "new_exam",
+                Text::NewExam,
+                &[
+                    "exam_id",
+                    "date",
+                    "days",
+                    "kind",
+                    "category",
+                    "word_id",
+                    "word_name",
+                    "price",
+                    "places",
+                ],
+                self.new_exam.as_ref(),
+            ),
+            (
+                "enrolled",
+                Text::Enrolled,
+                &["reservation_id", "exam_id", "deadline"],
+                self.enrolled.as_ref(),
+            ),
+            (
+                "status_change",
+                Text::StatusChange,
+                &[
+                    "reservation_id",
+                    "word_name",
+                    "date",
+                    "days",
+                    "previous",
+                    "status",
+                ],
+                self.status_change.as_ref(),
+            ),
+            ("error", Text::Error, &["error"], self.error.as_ref()),
+            (
+                "exam_reminder",
+                Text::ExamReminder,
+                &[
+                    "reservation_id",
+                    "word_name",
+                    "address",
+                    "date",
+                    "days",
+                    "time_left",
+                    "exam",
+                    "checklist",
+                ],
+                self.exam_reminder.as_ref(),
+            ),
+            (
+                "payment_reminder",
+                Text::PaymentReminder,
+                &[
+                    "header",
+                    "reservation_id",
+                    "word_name",
+                    "date",
+                    "days",
+                    "deadline",
+                    "minutes",
+                ],
+                self.payment_reminder.as_ref(),
+            ),
+        ]
+    }
+
+    /// Renders the message from the configured template, or from the built-in text in the
+    /// `language`
+    pub fn render(
+        &self,
+        text: Text,
+        language: Language,
+        fields: &[(&str, &(dyn fmt::Display + Sync))],
+    ) -> String {
+        let template = self
+            .templates()
+            .into_iter()
+            .find(|(_, template_text, _, _)| *template_text == text)
+            .and_then(|(_, _, _, template)| template?.get(language));
+        match template {
+            Some(template) => fill(template, fields),
+            None => language.format(text, fields),
+        }
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        for (name, _, fields, template) in self.templates() {
+            for field in template
+                .iter()
+                .flat_map(|template| template.all())
+                .flat_map(placeholders)
+            {
+                if !fields.contains(&field) {
+                    return Err(ConfigError::InvalidValue(
+                        format!("templates.{name}"),
+                        format!("unknown field '{field}', use {}", fields.join(", ")),
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    pub accounts: Vec<AccountConfig>,
+    /// Default watch criteria of all accounts
+    pub watch: WatchConfig,
+    #[serde(default)]
+    pub poll_intervals: PollIntervals,
+    #[serde(default)]
+    pub reminders: RemindersConfig,
+    #[serde(default)]
+    pub notifiers: NotifiersConfig,
+    pub api: Option<ApiConfig>,
+    #[serde(default)]
+    pub storage: StorageConfig,
+    #[serde(default)]
+    pub shutdown: ShutdownConfig,
+    #[serde(default)]
+    pub templates: TemplatesConfig,
+}
+
+impl Config {
+    /// Loads the configuration from a TOML file. Both the structured and the legacy (flat
+    /// `KEY="value"`) formats are accepted.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path)
+            .map_err(|err| ConfigError::IoError(path.to_path_buf(), err))?;
+        log::debug!("Loaded configuration from {}", path.display());
+        content.parse()
+    }
+
+    /// Loads the configuration from the first available source, reading the settings with `get`
+    /// (environment variables or shuttle secrets):
+    ///
+    /// 1. `EASYCAR_CONFIG_TOML` - the whole configuration as a TOML string,
+    /// 2. `EASYCAR_CONFIG` - a path to the configuration file,
+    /// 3. the [`CONFIG_FILE`] in the working directory,
+    /// 4. the legacy flat keys.
+    pub fn discover(get: impl Fn(&str) -> Option<String>) -> Result<Self, ConfigError> {
+        if let Some(content) = get("EASYCAR_CONFIG_TOML") {
+            log::debug!("Loading configuration from EASYCAR_CONFIG_TOML");
+            return content.parse();
+        }
+        match Self::discover_file(&get) {
+            Some(path) => Self::load(path),
+            None => {
+                log::warn!("Configuration file {CONFIG_FILE} not found, using the legacy keys");
+                Self::from_legacy(get)
+            }
+        }
+    }
+
+    /// Returns the configuration file read by [`Config::discover`], if there is one
+    pub fn discover_file(get: impl Fn(&str) -> Option<String>) -> Option<PathBuf> {
+        if get("EASYCAR_CONFIG_TOML").is_some() {
+            return None;
+        }
+        if let Some(path) = get("EASYCAR_CONFIG") {
+            return Some(path.into());
+        }
+        Path::new(CONFIG_FILE)
+            .exists()
+            .then(|| PathBuf::from(CONFIG_FILE))
+    }
+
+    /// Returns the configuration of the account with the given name
+    pub fn account(&self, name: &str) -> Option<&AccountConfig> {
+        self.accounts.iter().find(|account| account.name == name)
+    }
+
+    /// Returns the notification routes of the account
+    pub fn routes(&self, account: &AccountConfig) -> Vec<NotifyRoute> {
+        if !account.notify.is_empty() {
+            account.notify.clone()
+        } else if self.headless() || account.chat_id.is_none() {
+            vec![NotifyRoute::new("stdout")]
+        } else {
+            vec![NotifyRoute::new("telegram")]
+        }
+    }
+
+    /// Whether the service runs without the telegram bot
+    pub fn headless(&self) -> bool {
+        self.notifiers.telegram.is_none()
+    }
+
+    /// Returns the watch criteria of the account
+    pub fn watch(&self, account: &AccountConfig) -> WatchConfig {
+        account.watch.clone().unwrap_or(self.watch.clone())
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let invalid =
+            |field: String, reason: &str| Err(ConfigError::InvalidValue(field, reason.to_owned()));
+
+        if self.accounts.is_empty() {
+            return invalid("accounts".to_owned(), "at least one account is required");
+        }
+        if let Some(telegram) = &self.notifiers.telegram {
+            if telegram.token.is_empty() {
+                return invalid("notifiers.telegram.token".to_owned(), "is empty");
+            }
+            if let Some(webhook) = &telegram.webhook {
+                if reqwest::Url::parse(&webhook.url).is_err() {
+                    return invalid("notifiers.telegram.webhook.url".to_owned(), "invalid URL");
+                }
+                if webhook.secret_token.as_ref().is_some_and(|token| {
+                    !(1..=256).contains(&token.len())
+                        || !token
+                            .chars()
+                            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+                }) {
+                    return invalid(
+                        "notifiers.telegram.webhook.secret_token".to_owned(),
+                        "has to consist of 1-256 letters, digits, '_' or '-'",
+                    );
+                }
+            }
+        }
+
+        let mut names = HashSet::new();
+        let mut chats = HashSet::new();
+        for account in &self.accounts {
+            let context = format!("accounts.{}", account.name);
+            if account.name.is_empty() || !names.insert(&account.name) {
+                return invalid(format!("{context}.name"), "has to be unique and non-empty");
+            }
+            if account
+                .chat_id
+                .is_some_and(|chat_id| !chats.insert(chat_id))
+            {
+                return invalid(
+                    format!("{context}.chat_id"),
+                    "every account needs a separate chat",
+                );
+            }
+            for (field, value) in [
+                ("username", &account.username),
+                ("password", &account.password),
+                ("phone_number", &account.phone_number),
+            ] {
+                if value.is_empty() {
+                    return invalid(format!("{context}.{field}"), "is empty");
+                }
+            }
+            let (ProfileIdType::PKK(profile) | ProfileIdType::PKZ(profile)) =
+                &account.driver_profile;
+            if profile.is_empty() {
+                return invalid(format!("{context}.pkk"), "is empty");
+            }
+            if account.pesel.len() != 11 || !account.pesel.chars().all(|c| c.is_ascii_digit()) {
+                return invalid(format!("{context}.pesel"), "has to consist of 11 digits");
+            }
+            if let Some(watch) = &account.watch {
+                watch.validate(&format!("{context}.watch"))?;
+            }
+            let notifiers = self.notifiers.names();
+            for route in &account.notify {
+                if !notifiers.contains(&route.notifier) {
+                    return invalid(
+                        format!("{context}.notify"),
+                        &format!("unknown notifier '{}'", route.notifier),
+                    );
+                }
+                if route.notifier == "telegram" && account.chat_id.is_none() {
+                    return invalid(
+                        format!("{context}.chat_id"),
+                        "is required by the telegram notifier",
+                    );
+                }
+            }
+        }
+
+        self.watch.validate("watch")?;
+
+        for (field, interval) in [
+            ("exams", self.poll_intervals.exams),
+            ("reservations", self.poll_intervals.reservations),
+            ("payments", self.poll_intervals.payments),
+            ("reminders", self.poll_intervals.reminders),
+        ] {
+            if interval.0 <= Duration::zero() {
+                return invalid(format!("poll_intervals.{field}"), "has to be positive");
+            }
+        }
+        if self.reminders.payment_window.0 <= Duration::zero() {
+            return invalid("reminders.payment_window".to_owned(), "has to be positive");
+        }
+        if let Some(api) = &self.api {
+            if api.token.is_empty() {
+                return invalid("api.token".to_owned(), "is empty");
+            }
+            if let Some(dashboard) = &api.dashboard {
+                if dashboard.username.is_empty() || dashboard.password.is_empty() {
+                    return invalid(
+                        "api.dashboard".to_owned(),
+                        "the username and password can't be empty",
+                    );
+                }
+            }
+        }
+        self.templates.validate()?;
+
+        Ok(())
+    }
+}
+
+impl FromStr for Config {
+    type Err = ConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let table: toml::Table = match s.parse() {
+            Ok(table) => table,
+            // Legacy dotenv files may contain unquoted values, which aren't valid TOML
+            Err(err) => {
+                return match legacy_dotenv(s) {
+                    Some(values) => {
+                        log::warn!("Using the legacy configuration format");
+                        Config::from_legacy(|key| values.get(key).cloned())
+                    }
+                    None => Err(err.into()),
+                }
+            }
+        };
+        if table.contains_key("USERNAME") {
+            log::warn!("Using the legacy configuration format");
+            let values: LegacyConfig = table.try_into()?;
+            return Config::from_legacy(|key| values.get(key));
+        }
+
+        let config: Config = table.try_into()?;
+        config.validate()?;
+        Ok(config)
+    }
+}
+
+/// Parses a legacy configuration in the dotenv format, returns `None` if it isn't one
+fn legacy_dotenv(content: &str) -> Option<BTreeMap<String, String>> {
+    dotenvy::from_read_iter(content.as_bytes())
+        .collect::<Result<BTreeMap<_, _>, _>>()
+        .ok()
+        .filter(|values| values.contains_key("USERNAME"))
+}
+
+#[derive(Deserialize)]
+#[serde(transparent)]
+struct LegacyConfig(toml::Table);
+
+impl LegacyConfig {
+    fn get(&self, key: &str) -> Option<String> {
+        self.0.get(key).map(|v| match v {
+            toml::Value::String(v) => v.clone(),
+            // Numeric ids may be written without the quotes
+            other => other.to_string(),
+        })
+    }
+}
+
+impl Config {
+    /// Builds the configuration out of the legacy flat keys (`USERNAME`, `OSK_ID`, ...) returned
+    /// by `get`
+    pub fn from_legacy(get: impl Fn(&str) -> Option<String>) -> Result<Self, ConfigError> {
+        let required = |key: &'static str| {
+            get(key)
+                .filter(|v| !v.is_empty())
+                .ok_or(ConfigError::MissingKey(key))
+        };
+        let parse_error =
+            |key: &str, value: String| ConfigError::InvalidValue(key.to_owned(), value);
+
+        let osk_id = required("OSK_ID")?;
+        let osk_id = osk_id
+            .parse::<NonZeroU32>()
+            .map_err(|_| parse_error("OSK_ID", osk_id))?;
+        let optional = |key: &str| get(key).filter(|v| !v.is_empty());
+        let chat_id = optional("TELEGRAM_CHAT_ID")
+            .map(|chat_id| {
+                chat_id
+                    .parse()
+                    .map_err(|_| parse_error("TELEGRAM_CHAT_ID", chat_id))
+            })
+            .transpose()?;
+
+        let config = Config {
+            accounts: vec![AccountConfig {
+                name: "default".to_owned(),
+                username: required("USERNAME")?,
+                password: required("PASSWORD")?,
+                pesel: required("PESEL")?,
+                phone_number: required("PHONE_NUMBER")?,
+                driver_profile: ProfileIdType::PKK(required("PKK")?),
+                chat_id,
+                language: Language::default(),
+                watch: None,
+                calendar_file: optional("CALENDAR_FILE").map(Into::into),
+                notify: Vec::new(),
+            }],
+            watch: WatchConfig::new(osk_id),
+            poll_intervals: PollIntervals::default(),
+            reminders: RemindersConfig::default(),
+            notifiers: NotifiersConfig::new(optional("TELOXIDE_TOKEN").map(|token| {
+                TelegramConfig {
+                    token,
+                    admin_chat_id: None,
+                    webhook: None,
+                }
+            })),
+            api: None,
+            storage: StorageConfig::default(),
+            shutdown: ShutdownConfig::default(),
+            templates: TemplatesConfig::default(),
+        };
+
+        config.validate()?;
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"
+[[accounts]]
+name = "default"
+username = "user"
+password = "secret"
+pesel = "12345678901"
+phone_number = "123456789"
+pkk = "12345"
+chat_id = 1
+
+[watch]
+words = [3]
+
+[notifiers.telegram]
+token = "123:abc"
+"#;
+
+    fn config() -> Config {
+        CONFIG.parse().unwrap()
+    }
+
+    /// Returns the field reported by the validation of the changed configuration
+    fn invalid_field(change: impl FnOnce(&mut Config)) -> String {
+        let mut config = config();
+        change(&mut config);
+        match config.validate() {
+            Err(ConfigError::InvalidValue(field, _)) => field,
+            other => panic!("expected an invalid value, got {other:?}"),
+        }
+    }
+
+    fn duration(text: &str) -> HumanDuration {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn parses_the_example() {
+        let example = include_str!("../../Secrets.toml.example");
+        // The credentials are left empty in the example
+        assert!(matches!(
+            example.parse::<Config>(),
+            Err(ConfigError::InvalidValue(field, _)) if field == "notifiers.telegram.token"
+        ));
+
+        let filled = example
+            .replace(
+                "token = \"\"\n# The chat",
+                "token = \"123:abc\"\n# The chat",
+            )
+            .replace(
+                "username = \"\"\npassword = \"\"",
+                "username = \"user\"\npassword = \"secret\"",
+            )
+            .replace("pesel = \"\"", "pesel = \"12345678901\"")
+            .replace("phone_number = \"\"", "phone_number = \"123456789\"")
+            .replace("pkk = \"\"", "pkk = \"12345\"")
+            .replace("chat_id = 0", "chat_id = 1");
+        let config: Config = filled.parse().unwrap();
+        assert_eq!(config.accounts[0].name, "default");
+        assert_eq!(config.watch.words, vec![NonZeroU32::new(3).unwrap()]);
+        assert_eq!(
+            config.reminders.exam_offsets(),
+            vec![7 * 24 * 60, 24 * 60, 120]
+        );
+        assert!(!config.headless());
+    }
+
+    #[test]
+    fn parses_the_structured_format() {
+        let config = config();
+        let account = &config.accounts[0];
+        assert_eq!(
+            account.driver_profile,
+            ProfileIdType::PKK("12345".to_owned())
+        );
+        assert_eq!(account.language, Language::English);
+        assert_eq!(config.watch.categories, vec![LicenseCategory::B]);
+        assert_eq!(config.watch.exam_kinds, vec![ExamKind::Practice]);
+        assert_eq!(config.watch.horizon, duration("31d"));
+        assert_eq!(config.routes(account), vec![NotifyRoute::new("telegram")]);
+    }
+
+    #[test]
+    fn rejects_unknown_keys() {
+        let config = CONFIG.replace("words = [3]", "words = [3]\ncolour = \"red\"");
+        assert!(matches!(
+            config.parse::<Config>(),
+            Err(ConfigError::ParseError(_))
+        ));
+    }
+
+    const LEGACY_QUOTED: &str = r#"
+USERNAME="user"
+PASSWORD="secret"
+PESEL="12345678901"
+PHONE_NUMBER="123456789"
+PKK="12345"
+OSK_ID="3"
+TELEGRAM_CHAT_ID="1"
+TELOXIDE_TOKEN="123:abc"
+"#;
+
+    fn assert_legacy(config: &Config) {
+        let account = &config.accounts[0];
+        assert_eq!(account.name, "default");
+        assert_eq!(account.username, "user");
+        assert_eq!(account.password, "secret");
+        assert_eq!(account.pesel, "12345678901");
+        assert_eq!(
+            account.driver_profile,
+            ProfileIdType::PKK("12345".to_owned())
+        );
+        assert_eq!(account.chat_id, Some(1));
+        assert_eq!(config.watch.words, vec![NonZeroU32::new(3).unwrap()]);
+        assert_eq!(
+            config
+                .notifiers
+                .telegram
+                .as_ref()
+                .map(|telegram| telegram.token.as_str()),
+            Some("123:abc")
+        );
+    }
+
+    #[test]
+    fn converts_quoted_legacy_values() {
+        assert_legacy(&LEGACY_QUOTED.parse().unwrap());
+    }
+
+    #[test]
+    fn converts_unquoted_legacy_values() {
+        let config = LEGACY_QUOTED.replace('"', "");
+        assert_legacy(&config.parse().unwrap());
+
+        // Numbers are valid TOML even without the quotes
+        let config = LEGACY_QUOTED
+            .replace("OSK_ID=\"3\"", "OSK_ID=3")
+            .replace("TELEGRAM_CHAT_ID=\"1\"", "TELEGRAM_CHAT_ID=1");
+        assert_legacy(&config.parse().unwrap());
+    }
+
+    #[test]
+    fn converts_legacy_dotenv_syntax() {
+        let config = format!(
+            "# easycar\nexport USERNAME=user\n{}",
+            LEGACY_QUOTED
+                .replace("USERNAME=\"user\"\n", "")
+                .replace("PASSWORD=\"secret\"", "PASSWORD='secret'")
+        );
+        assert_legacy(&config.parse().unwrap());
+    }
+
+    #[test]
+    fn converts_legacy_keys() {
+        let values = legacy_dotenv(LEGACY_QUOTED).unwrap();
+        let config = Config::from_legacy(|key| values.get(key).cloned()).unwrap();
+        assert_legacy(&config);
+
+        let headless = Config::from_legacy(|key| {
+            values
+                .get(key)
+                .filter(|_| !["TELEGRAM_CHAT_ID", "TELOXIDE_TOKEN"].contains(&key))
+                .cloned()
+        })
+        .unwrap();
+        assert!(headless.headless());
+        assert_eq!(
+            headless.routes(&headless.accounts[0]),
+            vec![NotifyRoute::new("stdout")]
+        );
+    }
+
+    #[test]
+    fn reports_invalid_legacy_values() {
+        let config = LEGACY_QUOTED.replace("PESEL=\"12345678901\"\n", "");
+        assert!(matches!(
+            config.parse::<Config>(),
+            Err(ConfigError::MissingKey("PESEL"))
+        ));
+
+        let config = LEGACY_QUOTED.replace("OSK_ID=\"3\"", "OSK_ID=\"gdansk\"");
+        assert!(matches!(
+            config.parse::<Config>(),
+            Err(ConfigError::InvalidValue(field, value)) if field == "OSK_ID" && value == "gdansk"
+        ));
+
+        let config = LEGACY_QUOTED.replace("TELEGRAM_CHAT_ID=\"1\"", "TELEGRAM_CHAT_ID=me");
+        assert!(matches!(
+            config.parse::<Config>(),
+            Err(ConfigError::InvalidValue(field, _)) if field == "TELEGRAM_CHAT_ID"
+        ));
+    }
+
+    #[test]
+    fn reports_invalid_toml() {
+        assert!(matches!(
+            "[watch".parse::<Config>(),
+            Err(ConfigError::ParseError(_))
+        ));
+        // Neither TOML nor a legacy file
+        assert!(matches!(
+            "OSK_ID=3 4".parse::<Config>(),
+            Err(ConfigError::ParseError(_))
+        ));
+    }
+
+    #[test]
+    fn discovers_the_configuration() {
+        let config =
+            Config::discover(|key| (key == "EASYCAR_CONFIG_TOML").then(|| CONFIG.to_owned()))
+                .unwrap();
+        assert_eq!(config, self::config());
+
+        let missing = Config::discover(|key| {
+            (key == "EASYCAR_CONFIG").then(|| "/nonexistent/easycar.toml".to_owned())
+        });
+        assert!(matches!(missing, Err(ConfigError::IoError(..))));
+        assert_eq!(
+            Config::discover_file(|key| (key == "EASYCAR_CONFIG").then(|| "a.toml".to_owned())),
+            Some(PathBuf::from("a.toml"))
+        );
+    }
+
+    #[test]
+    fn validates_the_accounts() {
+        assert_eq!(invalid_field(|config| config.accounts.clear()), "accounts");
+        assert_eq!(
+            invalid_field(|config| config.accounts[0].name.clear()),
+            "accounts..name"
+        );
+        assert_eq!(
+            invalid_field(|config| {
+                let mut account = config.accounts[0].clone();
+                account.chat_id = Some(2);
+                config.accounts.push(account);
+            }),
+            "accounts.default.name"
+        );
+        assert_eq!(
+            invalid_field(|config| {
+                let mut account = config.accounts[0].clone();
+                account.name = "second".to_owned();
+                config.accounts.push(account);
+            }),
+            "accounts.second.chat_id"
+        );
+        assert_eq!(
+            invalid_field(|config| config.accounts[0].username.clear()),
+            "accounts.default.username"
+        );
+        assert_eq!(
+            invalid_field(|config| config.accounts[0].password.clear()),
+            "accounts.default.password"
+        );
+        assert_eq!(
+            invalid_field(|config| config.accounts[0].phone_number.clear()),
+            "accounts.default.phone_number"
+        );
+        assert_eq!(
+            invalid_field(|config| {
+                config.accounts[0].driver_profile = ProfileIdType::PKZ(String::new())
+            }),
+            "accounts.default.pkk"
+        );
+        assert_eq!(
+            invalid_field(|config| config.accounts[0].pesel = "1234567890a".to_owned()),
+            "accounts.default.pesel"
+        );
+        assert_eq!(
+            invalid_field(|config| config.accounts[0].pesel = "123".to_owned()),
+            "accounts.default.pesel"
+        );
+        assert_eq!(
+            invalid_field(|config| {
+                let mut watch = config.watch.clone();
+                watch.words.clear();
+                config.accounts[0].watch = Some(watch);
+            }),
+            "accounts.default.watch.words"
+        );
+        assert_eq!(
+            invalid_field(|config| config.accounts[0].notify = vec![NotifyRoute::new("ntfy.phone")]),
+            "accounts.default.notify"
+        );
+        assert_eq!(
+            invalid_field(|config| {
+                config.accounts[0].chat_id = None;
+                config.accounts[0].notify = vec![NotifyRoute::new("telegram")];
+            }),
+            "accounts.default.chat_id"
+        );
+    }
+
+    #[test]
+    fn validates_the_telegram_bot() {
+        let webhook = |url: &str, secret_token: Option<&str>| TelegramWebhookConfig {
+            url: url.to_owned(),
+            bind: default_webhook_bind(),
+            secret_token: secret_token.map(ToOwned::to_owned),
+        };
+        let set_webhook = |config: &mut Config, webhook| {
+            config.notifiers.telegram.as_mut().unwrap().webhook = Some(webhook)
+        };
+
+        assert_eq!(
+            invalid_field(|config| config.notifiers.telegram.as_mut().unwrap().token.clear()),
+            "notifiers.telegram.token"
+        );
+        assert_eq!(
+            invalid_field(|config| set_webhook(config, webhook("not a url", None))),
+            "notifiers.telegram.webhook.url"
+        );
+        for token in ["", "no spaces", &"a".repeat(257)] {
+            assert_eq!(
+                invalid_field(|config| {
+                    set_webhook(config, webhook("https://example.com", Some(token)))
+                }),
+                "notifiers.telegram.webhook.secret_token"
+            );
+        }
+
+        let mut config = config();
+        set_webhook(
+            &mut config,
+            webhook("https://example.com/telegram", Some("a-b_C1")),
+        );
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn validates_the_watch_criteria() {
+        assert_eq!(
+            invalid_field(|config| config.watch.words.clear()),
+            "watch.words"
+        );
+        assert_eq!(
+            invalid_field(|config| config.watch.categories.clear()),
+            "watch.categories"
+        );
+        assert_eq!(
+            invalid_field(|config| config.watch.exam_kinds.clear()),
+            "watch.exam_kinds"
+        );
+        assert_eq!(
+            invalid_field(|config| config.watch.horizon = duration("0d")),
+            "watch.horizon"
+        );
+        assert_eq!(
+            invalid_field(|config| config.watch.horizon = duration("367d")),
+            "watch.horizon"
+        );
+        assert_eq!(
+            invalid_field(|config| {
+                config.watch.filter.not_before = NaiveDate::from_ymd_opt(2025, 3, 1);
+                config.watch.filter.not_after = NaiveDate::from_ymd_opt(2025, 1, 1);
+            }),
+            "watch.filter"
+        );
+        assert_eq!(
+            invalid_field(|config| {
+                config.watch.filter.earliest_time = NaiveTime::from_hms_opt(16, 0, 0);
+                config.watch.filter.latest_time = NaiveTime::from_hms_opt(8, 0, 0);
+            }),
+            "watch.filter"
+        );
+    }
+
+    #[test]
+    fn validates_the_intervals_and_reminders() {
+        assert_eq!(
+            invalid_field(|config| config.poll_intervals.exams = duration("0s")),
+            "poll_intervals.exams"
+        );
+        assert_eq!(
+            invalid_field(|config| config.poll_intervals.reservations = duration("0s")),
+            "poll_intervals.reservations"
+        );
+        assert_eq!(
+            invalid_field(|config| config.poll_intervals.payments = duration("0s")),
+            "poll_intervals.payments"
+        );
+        assert_eq!(
+            invalid_field(|config| config.poll_intervals.reminders = duration("0s")),
+            "poll_intervals.reminders"
+        );
+        assert_eq!(
+            invalid_field(|config| config.reminders.payment_window = duration("0m")),
+            "reminders.payment_window"
+        );
+    }
+
+    #[test]
+    fn validates_the_api() {
+        let api = |token: &str, dashboard: Option<(&str, &str)>| ApiConfig {
+            bind: default_api_bind(),
+            token: token.to_owned(),
+            dashboard: dashboard.map(|(username, password)| DashboardConfig {
+                username: username.to_owned(),
+                password: password.to_owned(),
+                secure_cookie: false,
+            }),
+        };
+
+        assert_eq!(
+            invalid_field(|config| config.api = Some(api("", None))),
+            "api.token"
+        );
+        assert_eq!(
+            invalid_field(|config| config.api = Some(api("token", Some(("admin", ""))))),
+            "api.dashboard"
+        );
+
+        let mut config = config();
+        config.api = Some(api("token", Some(("admin", "secret"))));
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn validates_the_templates() {
+        assert_eq!(
+            invalid_field(|config| {
+                config.templates.enrolled =
+                    Some(Template::All("{reservation_id} {date}".to_owned()))
+            }),
+            "templates.enrolled"
+        );
+        assert_eq!(
+            invalid_field(|config| {
+                config.templates.error = Some(Template::Localized(LocalizedTemplate {
+                    en: Some("{error}".to_owned()),
+                    pl: Some("{blad}".to_owned()),
+                }))
+            }),
+            "templates.error"
+        );
+
+        let mut config = config();
+        config.templates.new_exam = Some(Template::All(
+            "{word_name}: {date} ({price} PLN)".to_owned(),
+        ));
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn built_in_templates_use_their_fields() {
+        for (name, text, fields, _) in TemplatesConfig::default().templates() {
+            for language in Language::ALL {
+                for field in placeholders(language.text(text)) {
+                    assert!(fields.contains(&field), "{name} ({language}): {field}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_durations() {
+        assert!("15".parse::<HumanDuration>().is_err());
+        assert!("1y".parse::<HumanDuration>().is_err());
+        assert!("9999999999999999w".parse::<HumanDuration>().is_err());
+        assert_eq!(duration("90m").to_string(), "90m");
+        assert_eq!(duration("120m").to_string(), "2h");
+    }
+}
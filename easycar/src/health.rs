@@ -0,0 +1,168 @@
+use std::{collections::BTreeMap, sync::Mutex};
+
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+
+/// How late a heartbeat or a poll may be before it's reported
+const GRACE_PERIOD: Duration = Duration::minutes(2);
+/// How long a crash is reported as a problem
+const CRASH_PERIOD: Duration = Duration::hours(1);
+
+struct Heartbeat {
+    last: DateTime<Utc>,
+    period: Duration,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Crash {
+    /// Number of crashes since the start
+    pub count: u32,
+    pub last: DateTime<Utc>,
+    pub error: String,
+}
+
+struct HealthState {
+    started: DateTime<Utc>,
+    last_poll: Option<DateTime<Utc>>,
+    last_poll_error: Option<String>,
+    last_token_refresh: Option<DateTime<Utc>>,
+    token_expires: Option<DateTime<Utc>>,
+    workers: BTreeMap<&'static str, Heartbeat>,
+    crashes: BTreeMap<&'static str, Crash>,
+}
+
+/// Liveness of the workers and of the session of an account
+pub struct Health {
+    state: Mutex<HealthState>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct HealthReport {
+    /// The workers are running and the session is valid
+    pub live: bool,
+    /// Live and the exams were recently searched successfully
+    pub healthy: bool,
+    pub problems: Vec<String>,
+    pub last_poll: Option<DateTime<Utc>>,
+    pub last_poll_error: Option<String>,
+    pub last_token_refresh: Option<DateTime<Utc>>,
+    pub token_expires: Option<DateTime<Utc>>,
+    /// The last heartbeat of every worker
+    pub workers: BTreeMap<&'static str, DateTime<Utc>>,
+    /// The crashed workers, which were restarted
+    pub crashes: BTreeMap<&'static str, Crash>,
+}
+
+impl Health {
+    pub fn new(token_expires: Option<DateTime<Utc>>) -> Self {
+        Self {
+            state: Mutex::new(HealthState {
+                started: Utc::now(),
+                last_poll: None,
+                last_poll_error: None,
+                last_token_refresh: None,
+                token_expires,
+                workers: BTreeMap::new(),
+                crashes: BTreeMap::new(),
+            }),
+        }
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, HealthState> {
+        self.state.lock().expect("Health lock poisoned")
+    }
+
+    /// Marks the worker as alive. It's expected to beat again within the `period`.
+    pub fn beat(&self, worker: &'static str, period: std::time::Duration) {
+        let period = Duration::from_std(period).unwrap_or(Duration::days(365));
+        self.state().workers.insert(
+            worker,
+            Heartbeat {
+                last: Utc::now(),
+                period,
+            },
+        );
+    }
+
+    pub fn poll_succeeded(&self) {
+        let mut state = self.state();
+        state.last_poll = Some(Utc::now());
+        state.last_poll_error = None;
+    }
+
+    pub fn poll_failed(&self, error: String) {
+        self.state().last_poll_error = Some(error);
+    }
+
+    pub fn token_refreshed(&self, expires: DateTime<Utc>) {
+        let mut state = self.state();
+        state.last_token_refresh = Some(Utc::now());
+        state.token_expires = Some(expires);
+    }
+
+    pub fn worker_crashed(&self, worker: &'static str, error: String) {
+        let mut state = self.state();
+        let count = state.crashes.get(worker).map_or(0, |crash| crash.count) + 1;
+        state.crashes.insert(
+            worker,
+            Crash {
+                count,
+                last: Utc::now(),
+                error,
+            },
+        );
+    }
+
+    /// Checks the heartbeats, the session and whether the exams were searched within three
+    /// `poll_interval`s
+    pub fn report(&self, poll_interval: Duration) -> HealthReport {
+        let state = self.state();
+        let now = Utc::now();
+        let mut problems = Vec::new();
+
+        for (worker, heartbeat) in &state.workers {
+            if heartbeat.last + heartbeat.period * 2 + GRACE_PERIOD < now {
+                problems.push(format!("The {worker} worker is not responding"));
+            }
+        }
+        match state.token_expires {
+            Some(expires) if expires < now => problems.push("The session expired".to_owned()),
+            None => problems.push("The session is not established".to_owned()),
+            _ => {}
+        }
+        let live = problems.is_empty();
+
+        let last_poll = state.last_poll.unwrap_or(state.started);
+        if last_poll + poll_interval * 3 + GRACE_PERIOD < now {
+            problems.push(match &state.last_poll_error {
+                Some(error) => format!("The exams can't be searched: {error}"),
+                None => "The exams weren't searched recently".to_owned(),
+            });
+        }
+
+        for (worker, crash) in &state.crashes {
+            if crash.last + CRASH_PERIOD > now {
+                problems.push(format!(
+                    "The {worker} worker crashed ({} times in total): {}",
+                    crash.count, crash.error
+                ));
+            }
+        }
+
+        HealthReport {
+            live,
+            healthy: problems.is_empty(),
+            problems,
+            last_poll: state.last_poll,
+            last_poll_error: state.last_poll_error.clone(),
+            last_token_refresh: state.last_token_refresh,
+            token_expires: state.token_expires,
+            workers: state
+                .workers
+                .iter()
+                .map(|(worker, heartbeat)| (*worker, heartbeat.last))
+                .collect(),
+            crashes: state.crashes.clone(),
+        }
+    }
+}
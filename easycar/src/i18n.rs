@@ -0,0 +1,773 @@
+use std::{fmt, str::FromStr};
+
+use chrono::{DateTime, Datelike, Duration, NaiveDateTime, Utc};
+use chrono_tz::Europe::Warsaw;
+use info_car_api::types::PossibleStatuses;
+use serde::{Deserialize, Serialize};
+
+use crate::config::ExamKind;
+
+/// Language of the messages sent to a chat
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub enum Language {
+    #[default]
+    #[serde(rename = "en")]
+    English,
+    #[serde(rename = "pl")]
+    Polish,
+}
+
+const WEEKDAYS: [[&str; 7]; 2] = [
+    ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"],
+    ["pon.", "wt.", "śr.", "czw.", "pt.", "sob.", "niedz."],
+];
+
+/// The Polish months are in the genitive case, as used in dates
+const MONTHS: [[&str; 12]; 2] = [
+    [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ],
+    [
+        "stycznia",
+        "lutego",
+        "marca",
+        "kwietnia",
+        "maja",
+        "czerwca",
+        "lipca",
+        "sierpnia",
+        "września",
+        "października",
+        "listopada",
+        "grudnia",
+    ],
+];
+
+impl Language {
+    pub const ALL: [Language; 2] = [Language::English, Language::Polish];
+
+    fn index(self) -> usize {
+        match self {
+            Language::English => 0,
+            Language::Polish => 1,
+        }
+    }
+
+    /// The name of the language in itself
+    pub fn name(self) -> &'static str {
+        match self {
+            Language::English => "English",
+            Language::Polish => "polski",
+        }
+    }
+
+    pub fn text(self, text: Text) -> &'static str {
+        text.translations()[self.index()]
+    }
+
+    /// Fills the `{field}` placeholders of the text
+    pub fn format(self, text: Text, fields: &[(&str, &(dyn fmt::Display + Sync))]) -> String {
+        fill(self.text(text), fields)
+    }
+
+    /// Returns a readable local date with hours in bold and underlined, e.g. `14:30 Mon 12 May 2025`
+    pub fn date(self, date: DateTime<Utc>) -> String {
+        self.naive_date(date.with_timezone(&Warsaw).naive_local())
+    }
+
+    /// Formats the local API timestamp like [`Self::date`]. Returns the timestamp if it's
+    /// malformed.
+    pub fn date_from_string(self, timestamp: &str) -> String {
+        NaiveDateTime::parse_from_str(timestamp, "%Y-%m-%dT%H:%M:%S")
+            .map(|date| self.naive_date(date))
+            .unwrap_or(timestamp.to_owned())
+    }
+
+    fn naive_date(self, date: NaiveDateTime) -> String {
+        format!(
+            "<u><b>{}</b></u> {} {} {} {}",
+            date.format("%H:%M"),
+            WEEKDAYS[self.index()][date.weekday().num_days_from_monday() as usize],
+            date.day(),
+            MONTHS[self.index()][date.month0() as usize],
+            date.year(),
+        )
+    }
+
+    pub fn time_delta(self, duration: Duration) -> String {
+        let days = duration.num_days();
+        let hours = duration.num_hours() % 24;
+        let minutes = duration.num_minutes() % 60;
+        let seconds = duration.num_seconds() % 60;
+        match self {
+            Language::English => {
+                format!("{days} days, {hours} hours, {minutes} minutes, {seconds} seconds")
+            }
+            Language::Polish => {
+                let days = match days {
+                    1 => "1 dzień".to_owned(),
+                    days => format!("{days} dni"),
+                };
+                format!("{days}, {hours} godz., {minutes} min, {seconds} s")
+            }
+        }
+    }
+
+    pub fn status(self, status: &PossibleStatuses) -> String {
+        match self {
+            Language::English => status.to_string(),
+            Language::Polish => match status {
+                PossibleStatuses::Created => "Utworzona",
+                PossibleStatuses::Draft => "Szkic",
+                PossibleStatuses::PaymentRejected => "Płatność odrzucona",
+                PossibleStatuses::PlaceReserved => "Miejsce zarezerwowane",
+                PossibleStatuses::SignupConfirmed => "Zapis potwierdzony",
+                PossibleStatuses::CancellationRequest => "Prośba o anulowanie",
+                PossibleStatuses::Cancelled => "Anulowana",
+                PossibleStatuses::Unknown => "Nieznany",
+            }
+            .to_owned(),
+        }
+    }
+
+    pub fn exam_kind(self, kind: ExamKind) -> String {
+        match (self, kind) {
+            (Language::English, kind) => kind.to_string(),
+            (Language::Polish, ExamKind::Theory) => "teoria".to_owned(),
+            (Language::Polish, ExamKind::Practice) => "praktyka".to_owned(),
+        }
+    }
+}
+
+impl FromStr for Language {
+    type Err = String;
+
+    /// Accepts the language codes, also with a region such as `pl-PL`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let code = s.trim().split(['-', '_']).next().unwrap_or_default();
+        match code.to_lowercase().as_str() {
+            "en" | "english" => Ok(Language::English),
+            "pl" | "polish" | "polski" => Ok(Language::Polish),
+            _ => Err(format!("Unknown language '{s}', use en or pl")),
+        }
+    }
+}
+
+impl fmt::Display for Language {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Language::English => write!(f, "en"),
+            Language::Polish => write!(f, "pl"),
+        }
+    }
+}
+
+/// Replaces the `{field}` placeholders with the values of the fields. Unknown placeholders are
+/// left as they are.
+pub fn fill(template: &str, fields: &[(&str, &(dyn fmt::Display + Sync))]) -> String {
+    let mut output = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        output.push_str(&rest[..start]);
+        let placeholder = &rest[start..];
+        let value = placeholder.find('}').and_then(|end| {
+            fields
+                .iter()
+                .find(|(name, _)| *name == &placeholder[1..end])
+                .map(|(_, value)| (value, end))
+        });
+        match value {
+            Some((value, end)) => {
+                output.push_str(&value.to_string());
+                rest = &placeholder[end + 1..];
+            }
+            None => {
+                output.push('{');
+                rest = &placeholder[1..];
+            }
+        }
+    }
+    output.push_str(rest);
+    output
+}
+
+/// The names of the `{field}` placeholders in the template
+pub fn placeholders(template: &str) -> impl Iterator<Item = &str> {
+    template.split('{').skip(1).filter_map(|part| {
+        let (name, _) = part.split_once('}')?;
+        (!name.is_empty() && name.chars().all(|c| c.is_ascii_lowercase() || c == '_'))
+            .then_some(name)
+    })
+}
+
+/// The messages of the bot. The ones in [`crate::config::TemplatesConfig`] can be customised.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Text {
+    Error,
+    TooFewArguments,
+    InvalidArgument,
+    Never,
+    Unknown,
+    None,
+    Yes,
+    No,
+    UnknownLowercase,
+    HelpHeader,
+    Uptime,
+    ChatId,
+    UnlinkedChat,
+    Language,
+    UnknownLanguage,
+    ReminderOffsets,
+    InvalidReminderOffset,
+    Reloaded,
+    ReloadFailed,
+    ReloadFailedNotification,
+    HealthOk,
+    HealthProblems,
+    HealthDates,
+    HealthWorker,
+    HealthCrash,
+    CurrentExam,
+    RoomNotAssigned,
+    Paid,
+    PaymentInProgress,
+    NotPaid,
+    NoActiveExam,
+    ExportedEvents,
+    Enrolled,
+    ReservationStatus,
+    ReservationCancelled,
+    PaidForExam,
+    WordsQueryMissing,
+    NoWordsMatch,
+    WordsHeader,
+    WordDetails,
+    WatchWordButton,
+    WordWatched,
+    PayPrompt,
+    BlikPlaceholder,
+    PayButton,
+    WorkingOnIt,
+    WaitingForWorkers,
+    LoggingIn,
+    FetchingActiveReservation,
+    FetchingReservations,
+    Enrolling,
+    FetchingStatus,
+    Cancelling,
+    Paying,
+    SearchingWords,
+    FetchingSchedule,
+    FetchingWordDetails,
+    AllReservations,
+    ActiveReservations,
+    CancelledReservations,
+    NoReservations,
+    NoFilteredReservations,
+    ReservationsHeader,
+    ReservationItem,
+    UnknownDate,
+    UnknownReservationFilter,
+    NoExams,
+    ExamsHeader,
+    ExamItem,
+    PreviousPage,
+    NextPage,
+    NewExam,
+    InvalidToken,
+    StatusChange,
+    NewStatus,
+    StatusMessage,
+    CancellationReason,
+    NewUnpaidReservation,
+    PaymentReminderHeader,
+    PaymentReminder,
+    ExamReminder,
+    ExamChecklist,
+    WorkerCrashed,
+    GoingOffline,
+    Settings,
+    WatchOverridden,
+    NotAWordId,
+    UnknownWatchAction,
+    WatchedWords,
+    WatchedCategories,
+    Horizon,
+    NotADayCount,
+    InvalidValue,
+    InvalidDate,
+    InvalidTime,
+    UnknownExamFilter,
+    ExamFilter,
+}
+
+impl Text {
+    /// The English and the Polish text
+    fn translations(self) -> [&'static str; 2] {
+        match self {
+            Text::Error => ["❌ Error: {error}", "❌ Błąd: {error}"],
+            Text::TooFewArguments => [
+                "Too few arguments! Expected: {expected}, got {got}!",
+                "Za mało argumentów! Oczekiwano: {expected}, podano: {got}!",
+            ],
+            Text::InvalidArgument => [
+                "Invalid argument: {message}",
+                "Nieprawidłowy argument: {message}",
+            ],
+            Text::Never => ["never", "nigdy"],
+            Text::Unknown => ["Unknown", "Nieznany"],
+            Text::None => ["None", "Brak"],
+            Text::Yes => ["yes", "tak"],
+            Text::No => ["no", "nie"],
+            Text::UnknownLowercase => ["unknown", "nieznany"],
+            Text::HelpHeader => ["These commands are supported:", "Dostępne polecenia:"],
+            Text::Uptime => ["The uptime is: {uptime}", "Czas działania: {uptime}"],
+            Text::ChatId => [
+                "The current ChatId is: <code>{chat_id}</code>",
+                "Identyfikator tego czatu to: <code>{chat_id}</code>",
+            ],
+            Text::UnlinkedChat => [
+                "This chat is not linked to any account. Set <code>chat_id = {chat_id}</code> in the configuration of your account.",
+                "Ten czat nie jest powiązany z żadnym kontem. Ustaw <code>chat_id = {chat_id}</code> w konfiguracji swojego konta.",
+            ],
+            Text::Language => [
+                "🌐 The language is {language}. Change it with /language en or /language pl",
+                "🌐 Język: {language}. Zmień go poleceniem /language en lub /language pl",
+            ],
+            Text::UnknownLanguage => [
+                "Unknown language '{language}'. Use en, pl or reset",
+                "Nieznany język '{language}'. Użyj en, pl lub reset",
+            ],
+            Text::ReminderOffsets => [
+                "The exam reminders are sent {offsets} before the exam",
+                "Przypomnienia o egzaminie są wysyłane {offsets} przed egzaminem",
+            ],
+            Text::InvalidReminderOffset => [
+                "Invalid offset '{offset}'. Use e.g. 30m, 2h, 1d or 1w",
+                "Nieprawidłowy czas '{offset}'. Użyj np. 30m, 2h, 1d lub 1w",
+            ],
+            Text::Reloaded => [
+                "🔄 The configuration was reloaded",
+                "🔄 Konfiguracja została przeładowana",
+            ],
+            Text::ReloadFailed => [
+                "❌ Error: {error}. The old configuration is kept",
+                "❌ Błąd: {error}. Zachowano poprzednią konfigurację",
+            ],
+            Text::ReloadFailedNotification => [
+                "❌ Failed to reload the configuration, keeping the old one: {error}",
+                "❌ Nie udało się przeładować konfiguracji, zachowano poprzednią: {error}",
+            ],
+            Text::HealthOk => ["✅ Everything works", "✅ Wszystko działa"],
+            Text::HealthProblems => ["❌ Problems:", "❌ Problemy:"],
+            Text::HealthDates => [
+                "Last exam search: {last_poll}\nLast token refresh: {last_token_refresh}\nThe token expires: {token_expires}",
+                "Ostatnie wyszukiwanie egzaminów: {last_poll}\nOstatnie odświeżenie tokenu: {last_token_refresh}\nToken wygasa: {token_expires}",
+            ],
+            Text::HealthWorker => [
+                "The {worker} last ran: {date}",
+                "Ostatnie uruchomienie ({worker}): {date}",
+            ],
+            Text::HealthCrash => [
+                "The {worker} crashed {count} times, last: {date}",
+                "Awarie ({worker}): {count}, ostatnia: {date}",
+            ],
+            Text::CurrentExam => [
+                "The current exam (<code>{id}</code>) is:\nWord: {word}\nAddress: {address}\nDate: {date}\nIn: {countdown}\nRoom: {room}\nOSK vehicle: {vehicle}\nPayment: {payment}",
+                "Aktualny egzamin (<code>{id}</code>):\nWORD: {word}\nAdres: {address}\nData: {date}\nZa: {countdown}\nSala: {room}\nPojazd OSK: {vehicle}\nPłatność: {payment}",
+            ],
+            Text::RoomNotAssigned => ["Not assigned", "Nie przydzielono"],
+            Text::Paid => ["Paid", "Opłacona"],
+            Text::PaymentInProgress => ["In progress ({payment})", "W toku ({payment})"],
+            Text::NotPaid => ["Not paid", "Nieopłacona"],
+            Text::NoActiveExam => [
+                "You don't have any active exam",
+                "Nie masz żadnego aktywnego egzaminu",
+            ],
+            Text::ExportedEvents => [
+                "Exported {count} events",
+                "Wyeksportowano wydarzenia: {count}",
+            ],
+            Text::Enrolled => [
+                "Enrolled to the exam! The reservation id is <code>{reservation_id}</code>\nPay for it before {deadline} or it will be lost.\nCheck the status using <code>/status {reservation_id}</code>",
+                "Zapisano na egzamin! Identyfikator rezerwacji to <code>{reservation_id}</code>\nOpłać ją przed {deadline}, inaczej przepadnie.\nSprawdź status poleceniem <code>/status {reservation_id}</code>",
+            ],
+            Text::ReservationStatus => [
+                "ID: {id}\nStatus: {status}\nReason: {reason}\nWord: {word}\nAddress: {address}\nCategory: {category}\nDate: {date}",
+                "ID: {id}\nStatus: {status}\nPowód: {reason}\nWORD: {word}\nAdres: {address}\nKategoria: {category}\nData: {date}",
+            ],
+            Text::ReservationCancelled => [
+                "Successfully canceled reservation: {id}",
+                "Anulowano rezerwację: {id}",
+            ],
+            Text::PaidForExam => [
+                "Paid for exam {id} with {amount} PLN.\nStatus: {status}",
+                "Opłacono egzamin {id} kwotą {amount} zł.\nStatus: {status}",
+            ],
+            Text::WordsQueryMissing => [
+                "Give a part of the WORD name, address or province",
+                "Podaj część nazwy, adresu lub województwa WORD",
+            ],
+            Text::NoWordsMatch => [
+                "No WORD centers match '{query}'",
+                "Żaden ośrodek WORD nie pasuje do '{query}'",
+            ],
+            Text::WordsHeader => ["The matching WORD centers are:", "Pasujące ośrodki WORD:"],
+            Text::WordDetails => [
+                "Offline: {offline}, rescheduling: {reschedule}",
+                "Offline: {offline}, zmiana terminu: {reschedule}",
+            ],
+            Text::WatchWordButton => ["➕ Watch {word}", "➕ Obserwuj {word}"],
+            Text::WordWatched => [
+                "WORD {id} is watched now. Watched WORDs: {words}",
+                "WORD {id} jest teraz obserwowany. Obserwowane WORD-y: {words}",
+            ],
+            Text::PayPrompt => [
+                "Reply to this message with a BLIK code to pay for the reservation ",
+                "Odpowiedz na tę wiadomość kodem BLIK, aby opłacić rezerwację ",
+            ],
+            Text::BlikPlaceholder => ["BLIK code", "Kod BLIK"],
+            Text::PayButton => ["💳 Pay with BLIK", "💳 Zapłać BLIK-iem"],
+            Text::WorkingOnIt => ["Working on it", "Pracuję nad tym"],
+            Text::WaitingForWorkers => [
+                "Waiting for the background checks",
+                "Czekam na zakończenie sprawdzania w tle",
+            ],
+            Text::LoggingIn => ["Logging in", "Logowanie"],
+            Text::FetchingActiveReservation => [
+                "Fetching the active reservation",
+                "Pobieranie aktywnej rezerwacji",
+            ],
+            Text::FetchingReservations => ["Fetching the reservations", "Pobieranie rezerwacji"],
+            Text::Enrolling => ["Enrolling to the exam {id}", "Zapisywanie na egzamin {id}"],
+            Text::FetchingStatus => [
+                "Fetching the status of the reservation {id}",
+                "Pobieranie statusu rezerwacji {id}",
+            ],
+            Text::Cancelling => ["Cancelling the reservation {id}", "Anulowanie rezerwacji {id}"],
+            Text::Paying => [
+                "Paying for the reservation {id}, confirm the payment in the bank app",
+                "Płatność za rezerwację {id}, potwierdź ją w aplikacji banku",
+            ],
+            Text::SearchingWords => ["Searching the WORD centers", "Wyszukiwanie ośrodków WORD"],
+            Text::FetchingSchedule => [
+                "Fetching the schedule of WORD {word} (category {category})",
+                "Pobieranie terminarza WORD {word} (kategoria {category})",
+            ],
+            Text::FetchingWordDetails => ["Fetching the WORD details", "Pobieranie danych WORD"],
+            Text::AllReservations => ["all", "wszystkie"],
+            Text::ActiveReservations => ["active", "aktywne"],
+            Text::CancelledReservations => ["cancelled", "anulowane"],
+            Text::NoReservations => [
+                "You don't have any reservations",
+                "Nie masz żadnych rezerwacji",
+            ],
+            Text::NoFilteredReservations => [
+                "You don't have any {filter} reservations",
+                "Nie masz żadnych rezerwacji ({filter})",
+            ],
+            Text::ReservationsHeader => [
+                "Reservations ({filter}), page {page} of {pages}:",
+                "Rezerwacje ({filter}), strona {page} z {pages}:",
+            ],
+            Text::ReservationItem => [
+                "• {date} in {word} ({status})\nID: <code>{id}</code>",
+                "• {date}, {word} ({status})\nID: <code>{id}</code>",
+            ],
+            Text::UnknownDate => ["unknown date", "nieznana data"],
+            Text::UnknownReservationFilter => [
+                "Unknown filter '{filter}', use all, active or cancelled",
+                "Nieznany filtr '{filter}', użyj all, active lub cancelled",
+            ],
+            Text::NoExams => [
+                "No exams meet the criteria",
+                "Żaden egzamin nie spełnia kryteriów",
+            ],
+            Text::ExamsHeader => [
+                "The available exams, page {page} of {pages}:",
+                "Dostępne egzaminy, strona {page} z {pages}:",
+            ],
+            Text::ExamItem => [
+                "Exam (<code>{id}</code>): {date} (in <b>{days}</b> days), {kind} {category} in WORD {word}",
+                "Egzamin (<code>{id}</code>): {date} (za <b>{days}</b> dni), {kind}, kategoria {category}, WORD {word}",
+            ],
+            Text::PreviousPage => ["◀️ Previous", "◀️ Poprzednia"],
+            Text::NextPage => ["Next ▶️", "Następna ▶️"],
+            Text::NewExam => [
+                "New {kind} exam ({category}) is available in WORD {word_id}! The next exam date is {date} (in <b>{days}</b> days) (ID: <code>{exam_id}</code>)",
+                "Nowy egzamin ({kind}, kategoria {category}) jest dostępny w WORD {word_id}! Najbliższy termin to {date} (za <b>{days}</b> dni) (ID: <code>{exam_id}</code>)",
+            ],
+            Text::InvalidToken => [
+                "The token was invalid reloging...",
+                "Token był nieprawidłowy, ponowne logowanie...",
+            ],
+            Text::StatusChange => [
+                "Reservation <code>{reservation_id}</code> ({word_name}, {date}) changed status: {previous} → <b>{status}</b>",
+                "Rezerwacja <code>{reservation_id}</code> ({word_name}, {date}) zmieniła status: {previous} → <b>{status}</b>",
+            ],
+            Text::NewStatus => ["New", "Nowa"],
+            Text::StatusMessage => ["Message: {message}", "Wiadomość: {message}"],
+            Text::CancellationReason => [
+                "Cancellation reason: {reason}",
+                "Powód anulowania: {reason}",
+            ],
+            Text::NewUnpaidReservation => [
+                "New unpaid reservation!",
+                "Nowa nieopłacona rezerwacja!",
+            ],
+            Text::PaymentReminderHeader => [
+                "⏰ Payment reminder!",
+                "⏰ Przypomnienie o płatności!",
+            ],
+            Text::PaymentReminder => [
+                "{header}\nReservation <code>{reservation_id}</code> ({word_name}, {date}) has to be paid before {deadline} (in <b>{minutes}</b> minutes) or it will be lost.\nPay using the button below or <code>/pay {reservation_id} BLIK_CODE</code>",
+                "{header}\nRezerwację <code>{reservation_id}</code> ({word_name}, {date}) trzeba opłacić przed {deadline} (za <b>{minutes}</b> min), inaczej przepadnie.\nZapłać przyciskiem poniżej lub poleceniem <code>/pay {reservation_id} KOD_BLIK</code>",
+            ],
+            Text::ExamReminder => [
+                "📅 Your exam is in {time_left}!\n{exam}\n\n{checklist}",
+                "📅 Twój egzamin jest za {time_left}!\n{exam}\n\n{checklist}",
+            ],
+            Text::ExamChecklist => [
+                "Checklist:\n• ID card or passport\n• Glasses or contact lenses if you need them to drive\n• Arrive at least 30 minutes before the exam",
+                "Lista kontrolna:\n• Dowód osobisty lub paszport\n• Okulary lub soczewki, jeśli potrzebujesz ich do jazdy\n• Przyjdź co najmniej 30 minut przed egzaminem",
+            ],
+            Text::WorkerCrashed => [
+                "💥 {account}The {worker} worker crashed: <code>{error}</code>\nRestarting it in {delay}",
+                "💥 {account}Wątek {worker} uległ awarii: <code>{error}</code>\nPonowne uruchomienie za {delay}",
+            ],
+            Text::GoingOffline => [
+                "💤 EasyCar is going offline",
+                "💤 EasyCar przechodzi w tryb offline",
+            ],
+            Text::Settings => [
+                "<b>Watch</b>{overridden}\nWORDs: {words}\nCategories: {categories}\nExam kinds: {exam_kinds}\nHorizon: {horizon}\nFilter: {filter}\n\n<b>Poll intervals</b>\nExams: {exams}, reservations: {reservations}, payments: {payments}, reminders: {reminders}\n\n<b>Reminders</b>\nPayment: {payment} before the deadline ({payment_window} to pay)\nExam: {exam} before the exam\n\n<b>Language</b>: {language}",
+                "<b>Obserwowanie</b>{overridden}\nWORD-y: {words}\nKategorie: {categories}\nRodzaje egzaminów: {exam_kinds}\nHoryzont: {horizon}\nFiltr: {filter}\n\n<b>Częstotliwość sprawdzania</b>\nEgzaminy: {exams}, rezerwacje: {reservations}, płatności: {payments}, przypomnienia: {reminders}\n\n<b>Przypomnienia</b>\nPłatność: {payment} przed terminem ({payment_window} na zapłatę)\nEgzamin: {exam} przed egzaminem\n\n<b>Język</b>: {language}",
+            ],
+            Text::WatchOverridden => [
+                " (changed from Telegram, /watch reset restores the configuration)",
+                " (zmienione w Telegramie, /watch reset przywraca konfigurację)",
+            ],
+            Text::NotAWordId => [
+                "'{word}' is not a WORD id",
+                "'{word}' nie jest identyfikatorem WORD",
+            ],
+            Text::UnknownWatchAction => [
+                "Unknown action '{action}'. Use add, remove or reset",
+                "Nieznana akcja '{action}'. Użyj add, remove lub reset",
+            ],
+            Text::WatchedWords => ["Watched WORDs: {words}", "Obserwowane WORD-y: {words}"],
+            Text::WatchedCategories => [
+                "Watched categories: {categories}",
+                "Obserwowane kategorie: {categories}",
+            ],
+            Text::Horizon => [
+                "Looking for exams up to {horizon} ahead",
+                "Szukam egzaminów do {horizon} naprzód",
+            ],
+            Text::NotADayCount => [
+                "'{value}' is not a number of days",
+                "'{value}' nie jest liczbą dni",
+            ],
+            Text::InvalidValue => [
+                "Invalid value of {key}: '{value}'",
+                "Nieprawidłowa wartość {key}: '{value}'",
+            ],
+            Text::InvalidDate => [
+                "Invalid value of {key}: '{value}', use YYYY-MM-DD",
+                "Nieprawidłowa wartość {key}: '{value}', użyj RRRR-MM-DD",
+            ],
+            Text::InvalidTime => [
+                "Invalid value of {key}: '{value}', use HH:MM",
+                "Nieprawidłowa wartość {key}: '{value}', użyj GG:MM",
+            ],
+            Text::UnknownExamFilter => [
+                "Unknown filter '{key}'. Use not_before, not_after, weekdays, earliest_time, latest_time, min_places, max_price or clear",
+                "Nieznany filtr '{key}'. Użyj not_before, not_after, weekdays, earliest_time, latest_time, min_places, max_price lub clear",
+            ],
+            Text::ExamFilter => ["Filter: {filter}", "Filtr: {filter}"],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::config::{LocalizedTemplate, Template, TemplatesConfig};
+
+    #[test]
+    fn fills_placeholders() {
+        assert_eq!(
+            fill("{word} on {date}", &[("word", &"Gdańsk"), ("date", &12)]),
+            "Gdańsk on 12"
+        );
+        assert_eq!(fill("{a}{b}", &[("a", &1), ("b", &2)]), "12");
+        assert_eq!(fill("no fields", &[("a", &1)]), "no fields");
+    }
+
+    #[test]
+    fn fills_repeated_placeholders() {
+        assert_eq!(fill("{id}, {id}", &[("id", &"r-1")]), "r-1, r-1");
+        // The first of the repeated fields is used
+        assert_eq!(fill("{id}", &[("id", &1), ("id", &2)]), "1");
+    }
+
+    #[test]
+    fn keeps_unknown_placeholders() {
+        assert_eq!(fill("{missing} {id}", &[("id", &1)]), "{missing} 1");
+        assert_eq!(fill("{}", &[("", &1)]), "1");
+        assert_eq!(fill("{id", &[("id", &1)]), "{id");
+        assert_eq!(fill("{ {id}", &[("id", &1)]), "{ 1");
+        assert_eq!(fill("}{id}{", &[("id", &1)]), "}1{");
+    }
+
+    #[test]
+    fn doesnt_fill_values() {
+        assert_eq!(fill("{a} {b}", &[("a", &"{b}"), ("b", &"x")]), "{b} x");
+    }
+
+    #[test]
+    fn finds_placeholders() {
+        assert_eq!(
+            placeholders("{word_name}: {date} {date}").collect::<Vec<_>>(),
+            ["word_name", "date", "date"]
+        );
+        assert_eq!(
+            placeholders("{} {Date} {a b} {unclosed").count(),
+            0,
+            "only lowercase names are placeholders"
+        );
+        assert_eq!(placeholders("{{id}}").collect::<Vec<_>>(), ["id"]);
+    }
+
+    #[test]
+    fn translations_use_the_same_placeholders() {
+        for text in [
+            Text::Error,
+            Text::TooFewArguments,
+            Text::InvalidValue,
+            Text::InvalidDate,
+            Text::UnknownExamFilter,
+            Text::NewExam,
+            Text::Enrolled,
+            Text::StatusChange,
+            Text::ExamReminder,
+            Text::PaymentReminder,
+            Text::ReservationStatus,
+            Text::ReservationsHeader,
+            Text::ExamItem,
+        ] {
+            let mut english = placeholders(Language::English.text(text)).collect::<Vec<_>>();
+            let mut polish = placeholders(Language::Polish.text(text)).collect::<Vec<_>>();
+            english.sort_unstable();
+            polish.sort_unstable();
+            assert_eq!(english, polish, "{text:?}");
+        }
+    }
+
+    #[test]
+    fn renders_templates() {
+        let fields: &[(&str, &(dyn fmt::Display + Sync))] = &[("error", &"timeout")];
+        let mut templates = TemplatesConfig::default();
+        assert_eq!(
+            templates.render(Text::Error, Language::Polish, fields),
+            "❌ Błąd: timeout"
+        );
+
+        templates.error = Some(Template::Localized(LocalizedTemplate {
+            en: Some("Oops: {error}".to_owned()),
+            pl: None,
+        }));
+        assert_eq!(
+            templates.render(Text::Error, Language::English, fields),
+            "Oops: timeout"
+        );
+        // The built-in text is used for the missing languages
+        assert_eq!(
+            templates.render(Text::Error, Language::Polish, fields),
+            "❌ Błąd: timeout"
+        );
+
+        // A missing field is kept, the configuration validation reports it
+        templates.error = Some(Template::All("{error} ({code})".to_owned()));
+        assert_eq!(
+            templates.render(Text::Error, Language::English, fields),
+            "timeout ({code})"
+        );
+    }
+
+    #[test]
+    fn formats_dates_in_warsaw() {
+        let summer = Utc.with_ymd_and_hms(2025, 5, 12, 12, 30, 0).unwrap();
+        assert_eq!(
+            Language::English.date(summer),
+            "<u><b>14:30</b></u> Mon 12 May 2025"
+        );
+        assert_eq!(
+            Language::Polish.date(summer),
+            "<u><b>14:30</b></u> pon. 12 maja 2025"
+        );
+
+        let winter = Utc.with_ymd_and_hms(2025, 12, 31, 23, 15, 0).unwrap();
+        assert_eq!(
+            Language::Polish.date(winter),
+            "<u><b>00:15</b></u> czw. 1 stycznia 2026"
+        );
+    }
+
+    #[test]
+    fn formats_api_timestamps() {
+        assert_eq!(
+            Language::English.date_from_string("2025-10-05T08:00:00"),
+            "<u><b>08:00</b></u> Sun 5 Oct 2025"
+        );
+        assert_eq!(Language::Polish.date_from_string("soon"), "soon");
+    }
+
+    #[test]
+    fn formats_time_deltas() {
+        let delta = Duration::days(1) + Duration::hours(2) + Duration::seconds(61);
+        assert_eq!(
+            Language::English.time_delta(delta),
+            "1 days, 2 hours, 1 minutes, 1 seconds"
+        );
+        assert_eq!(
+            Language::Polish.time_delta(delta),
+            "1 dzień, 2 godz., 1 min, 1 s"
+        );
+        assert_eq!(
+            Language::Polish.time_delta(Duration::days(5)),
+            "5 dni, 0 godz., 0 min, 0 s"
+        );
+        assert_eq!(
+            Language::Polish.time_delta(Duration::zero()),
+            "0 dni, 0 godz., 0 min, 0 s"
+        );
+    }
+
+    #[test]
+    fn parses_languages() {
+        assert_eq!("pl-PL".parse(), Ok(Language::Polish));
+        assert_eq!(" EN_us ".parse(), Ok(Language::English));
+        assert_eq!("polski".parse(), Ok(Language::Polish));
+        assert!("de".parse::<Language>().is_err());
+        for language in Language::ALL {
+            assert_eq!(language.to_string().parse(), Ok(language));
+        }
+    }
+
+    #[test]
+    fn translates_statuses_and_kinds() {
+        assert_eq!(
+            Language::Polish.status(&PossibleStatuses::Cancelled),
+            "Anulowana"
+        );
+        assert_eq!(
+            Language::English.status(&PossibleStatuses::PlaceReserved),
+            "Place Reserved"
+        );
+        assert_eq!(Language::Polish.exam_kind(ExamKind::Theory), "teoria");
+    }
+}
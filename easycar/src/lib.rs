@@ -1,5 +1,15 @@
+pub mod account;
+#[cfg(feature = "api")]
+pub mod api;
+pub mod cli;
 mod client;
+pub mod config;
+pub mod health;
+pub mod i18n;
+pub mod notifier;
 pub mod service;
+pub mod state;
 pub mod utils;
+pub mod words;
 
 pub use client::UserData;
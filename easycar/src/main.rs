@@ -1,40 +1,21 @@
-use std::num::NonZeroU32;
-
-use easycar::{service::EasyCarService, UserData};
-use info_car_api::types::ProfileIdType;
+use easycar::service::EasyCarService;
 
 #[cfg(not(feature = "shuttle"))]
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    pretty_env_logger::init();
-    dotenvy::from_filename_override("Secrets.toml")?;
+    use clap::Parser;
+    use easycar::cli::Cli;
 
-    // Get UserData
-    let username = dotenvy::var("USERNAME")?;
-    let password = dotenvy::var("PASSWORD")?;
-    let pesel = dotenvy::var("PESEL")?;
-    let phone_number = dotenvy::var("PHONE_NUMBER")?;
-    let pkk = dotenvy::var("PKK")?;
-    let osk_id: u32 = dotenvy::var("OSK_ID")?.parse()?;
+    pretty_env_logger::init();
 
-    let user_data = UserData::new(
-        username,
-        password,
-        NonZeroU32::try_from(osk_id).expect("Osk_id is not a positive integer"),
-    );
+    let cli = Cli::parse();
+    let (config, config_file) = cli.load_config(|key| dotenvy::var(key).ok())?;
 
-    let chat_id = dotenvy::var("TELEGRAM_CHAT_ID")?;
-    let teloxide_token = dotenvy::var("TELOXIDE_TOKEN")?;
+    if cli.run(&config).await? {
+        return Ok(());
+    }
 
-    let service = EasyCarService::new(
-        teloxide_token,
-        user_data,
-        chat_id,
-        pesel,
-        phone_number,
-        ProfileIdType::PKK(pkk),
-    )
-    .await?;
+    let service = EasyCarService::new(config, config_file).await?;
     service.start().await.expect("Service error");
 
     Ok(())
@@ -45,34 +26,12 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 async fn main(
     #[shuttle_runtime::Secrets] secrets: shuttle_runtime::SecretStore,
 ) -> Result<EasyCarService, shuttle_runtime::Error> {
-    let username = secrets.get("USERNAME").expect("No USRENAME provided!");
-    let password = secrets.get("PASSWORD").expect("No PASSWORD provided!");
-    let pesel = secrets.get("PESEL").expect("No PESEL provided!");
-    let phone_number = secrets
-        .get("PHONE_NUMBER")
-        .expect("No PHONE_NUMBER provided!");
-    let pkk = secrets.get("PKK").expect("No PKK provided!");
-    let osk_id: u32 = 3;
-
-    let user_data = UserData::new(
-        username,
-        password,
-        NonZeroU32::try_from(osk_id).expect("Osk_id is not a positive integer"),
-    );
+    use easycar::config::Config;
 
-    let chat_id = secrets.get("TELEGRAM_CHAT_ID").unwrap();
-    let teloxide_key = secrets
-        .get("TELOXIDE_TOKEN")
-        .expect("You need a teloxide key set for this to work!");
+    let config = Config::discover(|key| secrets.get(key))
+        .map_err(|err| shuttle_runtime::Error::Custom(err.into()))?;
 
-    Ok(EasyCarService::new(
-        teloxide_key,
-        user_data,
-        chat_id,
-        pesel,
-        phone_number,
-        ProfileIdType::PKK(pkk),
-    )
-    .await
-    .unwrap())
+    EasyCarService::new(config, Config::discover_file(|key| secrets.get(key)))
+        .await
+        .map_err(|err| shuttle_runtime::Error::Custom(err.into()))
 }
@@ -0,0 +1,42 @@
+use std::process::Stdio;
+
+use async_trait::async_trait;
+use tokio::{process::Command, time::timeout};
+
+use super::{Notification, Notifier, NotifyError, NOTIFY_TIMEOUT};
+use crate::config::CommandConfig;
+
+/// Runs a local program for every notification
+pub struct CommandNotifier {
+    config: CommandConfig,
+}
+
+impl CommandNotifier {
+    pub fn new(config: CommandConfig) -> Self {
+        Self { config }
+    }
+}
+
+#[async_trait]
+impl Notifier for CommandNotifier {
+    async fn notify(&self, notification: &Notification) -> Result<(), NotifyError> {
+        let mut command = Command::new(&self.config.program);
+        command
+            .args(&self.config.args)
+            .env("EASYCAR_EVENT", notification.event.to_string())
+            .env("EASYCAR_ACCOUNT", &notification.account)
+            .env("EASYCAR_TITLE", notification.title())
+            .env("EASYCAR_TEXT", notification.text())
+            .env("EASYCAR_HTML", &notification.html)
+            .stdin(Stdio::null())
+            // A hanging program is killed when the timeout drops it
+            .kill_on_drop(true);
+        let status = timeout(NOTIFY_TIMEOUT, command.status())
+            .await
+            .map_err(|_| NotifyError::CommandTimedOut(NOTIFY_TIMEOUT))??;
+        if !status.success() {
+            return Err(NotifyError::CommandFailed(status));
+        }
+        Ok(())
+    }
+}
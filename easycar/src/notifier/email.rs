@@ -0,0 +1,76 @@
+use async_trait::async_trait;
+use lettre::{
+    message::{Mailbox, MultiPart},
+    transport::smtp::authentication::Credentials,
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+};
+
+use super::{Notification, Notifier, NotifyError};
+use crate::config::EmailConfig;
+
+/// Sends the notifications by email
+pub struct EmailNotifier {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+    to: Vec<Mailbox>,
+}
+
+impl EmailNotifier {
+    pub fn new(name: &str, config: &EmailConfig) -> Result<Self, NotifyError> {
+        let invalid = |reason: String| NotifyError::InvalidConfig(name.to_owned(), reason);
+
+        let builder = if config.starttls {
+            AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.server)
+        } else {
+            AsyncSmtpTransport::<Tokio1Executor>::relay(&config.server)
+        }
+        .map_err(|err| invalid(err.to_string()))?;
+        let builder = match config.port {
+            Some(port) => builder.port(port),
+            None => builder,
+        };
+        let transport = builder
+            .credentials(Credentials::new(
+                config.username.clone(),
+                config.password.clone(),
+            ))
+            .build();
+
+        let parse = |address: &String| {
+            address
+                .parse::<Mailbox>()
+                .map_err(|err| invalid(format!("invalid address '{address}': {err}")))
+        };
+        if config.to.is_empty() {
+            return Err(invalid("at least one recipient is required".to_owned()));
+        }
+        Ok(Self {
+            transport,
+            from: parse(&config.from)?,
+            to: config.to.iter().map(parse).collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+#[async_trait]
+impl Notifier for EmailNotifier {
+    async fn notify(&self, notification: &Notification) -> Result<(), NotifyError> {
+        let mut message = Message::builder().from(self.from.clone()).subject(format!(
+            "EasyCar: {} ({})",
+            notification.title(),
+            notification.account
+        ));
+        for to in &self.to {
+            message = message.to(to.clone());
+        }
+        let message = message
+            .multipart(MultiPart::alternative_plain_html(
+                notification.text(),
+                notification.html.replace('\n', "<br>"),
+            ))
+            .expect("The recipients are validated in the constructor");
+
+        self.transport.send(message).await?;
+        Ok(())
+    }
+}
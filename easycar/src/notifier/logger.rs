@@ -0,0 +1,66 @@
+use std::io::Write;
+
+use async_trait::async_trait;
+use chrono::Utc;
+use serde_json::json;
+use tokio::{fs::OpenOptions, io::AsyncWriteExt};
+
+use super::{Notification, Notifier, NotifyError};
+use crate::config::{LogConfig, LogFormat};
+
+/// Writes the notifications to the standard output or to a file, as text or JSON lines
+pub struct LogNotifier {
+    config: LogConfig,
+}
+
+impl LogNotifier {
+    pub fn new(config: LogConfig) -> Self {
+        Self { config }
+    }
+
+    fn format(&self, notification: &Notification) -> String {
+        let time = Utc::now().to_rfc3339();
+        match self.config.format {
+            LogFormat::Text => format!(
+                "{time} [{}] {}\n{}\n\n",
+                notification.account,
+                notification.title(),
+                notification.text()
+            ),
+            LogFormat::Json => {
+                let line = json!({
+                    "time": time,
+                    "event": notification.event,
+                    "account": notification.account,
+                    "title": notification.title(),
+                    "text": notification.text(),
+                    "html": notification.html,
+                });
+                format!("{line}\n")
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Notifier for LogNotifier {
+    async fn notify(&self, notification: &Notification) -> Result<(), NotifyError> {
+        let entry = self.format(notification);
+        match &self.config.path {
+            Some(path) => {
+                let mut file = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .await?;
+                file.write_all(entry.as_bytes()).await?;
+            }
+            None => {
+                let mut stdout = std::io::stdout().lock();
+                stdout.write_all(entry.as_bytes())?;
+                stdout.flush()?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,76 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use async_trait::async_trait;
+use chrono::Utc;
+use reqwest::Url;
+use serde_json::json;
+
+use super::{http_client, Notification, Notifier, NotifyError};
+use crate::config::MatrixConfig;
+
+/// Sends the notifications to a Matrix room using the client-server API
+pub struct MatrixNotifier {
+    client: reqwest::Client,
+    homeserver: Url,
+    config: MatrixConfig,
+    /// Makes the transaction ids unique
+    counter: AtomicU64,
+}
+
+impl MatrixNotifier {
+    pub fn new(name: &str, config: MatrixConfig) -> Result<Self, NotifyError> {
+        let homeserver = Url::parse(&config.homeserver)
+            .map_err(|err| NotifyError::InvalidConfig(name.to_owned(), err.to_string()))?;
+        if homeserver.cannot_be_a_base() {
+            return Err(NotifyError::InvalidConfig(
+                name.to_owned(),
+                "the homeserver has to be an HTTP URL".to_owned(),
+            ));
+        }
+        Ok(Self {
+            client: http_client(),
+            homeserver,
+            config,
+            counter: AtomicU64::new(0),
+        })
+    }
+}
+
+#[async_trait]
+impl Notifier for MatrixNotifier {
+    async fn notify(&self, notification: &Notification) -> Result<(), NotifyError> {
+        let transaction_id = format!(
+            "easycar-{}-{}",
+            Utc::now().timestamp_millis(),
+            self.counter.fetch_add(1, Ordering::Relaxed)
+        );
+        let mut url = self.homeserver.clone();
+        url.path_segments_mut()
+            .expect("Checked in the constructor")
+            .pop_if_empty()
+            .extend([
+                "_matrix",
+                "client",
+                "v3",
+                "rooms",
+                &self.config.room_id,
+                "send",
+                "m.room.message",
+                &transaction_id,
+            ]);
+
+        self.client
+            .put(url)
+            .bearer_auth(&self.config.access_token)
+            .json(&json!({
+                "msgtype": "m.text",
+                "body": notification.text(),
+                "format": "org.matrix.custom.html",
+                "formatted_body": notification.html.replace('\n', "<br>"),
+            }))
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
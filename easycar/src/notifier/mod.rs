@@ -0,0 +1,298 @@
+mod command;
+#[cfg(feature = "email")]
+mod email;
+mod logger;
+mod matrix;
+mod ntfy;
+mod telegram;
+mod webhook;
+
+use std::{fmt, process::ExitStatus, sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use metrics::counter;
+use serde::{Deserialize, Serialize};
+use teloxide::{types::InlineKeyboardMarkup, Bot, RequestError};
+use thiserror::Error;
+
+use crate::{
+    config::{AccountConfig, Config, LogConfig, NotifyRoute},
+    utils::html_to_text,
+};
+pub use command::CommandNotifier;
+#[cfg(feature = "email")]
+pub use email::EmailNotifier;
+pub use logger::LogNotifier;
+pub use matrix::MatrixNotifier;
+pub use ntfy::NtfyNotifier;
+pub use telegram::TelegramNotifier;
+pub use webhook::WebhookNotifier;
+
+/// Kinds of the notifications, used for routing them to the notifiers
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Event {
+    /// A new closest exam was found
+    NewExam,
+    /// A reservation changed its status
+    StatusChange,
+    /// A reservation has to be paid
+    Payment,
+    /// The exam is coming
+    ExamReminder,
+    /// The configuration was reloaded
+    Reload,
+    /// Something went wrong
+    Error,
+    /// The daemon is shutting down
+    Shutdown,
+}
+
+impl Event {
+    pub fn title(&self) -> &'static str {
+        match self {
+            Event::NewExam => "New exam",
+            Event::StatusChange => "Reservation status changed",
+            Event::Payment => "Payment reminder",
+            Event::ExamReminder => "Exam reminder",
+            Event::Reload => "Configuration reloaded",
+            Event::Error => "Error",
+            Event::Shutdown => "Going offline",
+        }
+    }
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Event::NewExam => "new_exam",
+            Event::StatusChange => "status_change",
+            Event::Payment => "payment",
+            Event::ExamReminder => "exam_reminder",
+            Event::Reload => "reload",
+            Event::Error => "error",
+            Event::Shutdown => "shutdown",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// A place shown together with the notification
+#[derive(Clone, Debug, PartialEq)]
+pub struct Venue {
+    pub latitude: f64,
+    pub longitude: f64,
+    pub title: String,
+    pub address: String,
+}
+
+#[derive(Clone, Debug)]
+pub struct Notification {
+    pub event: Event,
+    /// Name of the account the notification is about
+    pub account: String,
+    /// The message formatted with the telegram subset of HTML
+    pub html: String,
+    /// Buttons, only shown by telegram
+    pub keyboard: Option<InlineKeyboardMarkup>,
+    pub venue: Option<Venue>,
+}
+
+impl Notification {
+    pub fn new(event: Event, account: impl Into<String>, html: impl Into<String>) -> Self {
+        Self {
+            event,
+            account: account.into(),
+            html: html.into(),
+            keyboard: None,
+            venue: None,
+        }
+    }
+
+    pub fn with_keyboard(mut self, keyboard: InlineKeyboardMarkup) -> Self {
+        self.keyboard = Some(keyboard);
+        self
+    }
+
+    pub fn with_venue(mut self, venue: Venue) -> Self {
+        self.venue = Some(venue);
+        self
+    }
+
+    pub fn title(&self) -> &'static str {
+        self.event.title()
+    }
+
+    /// Returns the message as plain text, including the venue
+    pub fn text(&self) -> String {
+        let mut text = html_to_text(&self.html);
+        if let Some(venue) = &self.venue {
+            text += &format!(
+                "\n\n📍 {}, {} ({}, {})",
+                venue.title, venue.address, venue.latitude, venue.longitude
+            );
+        }
+        text
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum NotifyError {
+    #[error(transparent)]
+    TelegramError(#[from] RequestError),
+    #[error(transparent)]
+    HttpError(#[from] reqwest::Error),
+    #[cfg(feature = "email")]
+    #[error(transparent)]
+    EmailError(#[from] lettre::transport::smtp::Error),
+    #[error(transparent)]
+    IoError(#[from] std::io::Error),
+    #[error("The command exited with {0}")]
+    CommandFailed(ExitStatus),
+    #[error("The command didn't finish within {} seconds", .0.as_secs())]
+    CommandTimedOut(Duration),
+    #[error("Invalid configuration of the {0} notifier: {1}")]
+    InvalidConfig(String, String),
+    #[error("The {0} notifier is not available")]
+    Unavailable(String),
+}
+
+#[async_trait]
+pub trait Notifier: Send + Sync {
+    async fn notify(&self, notification: &Notification) -> Result<(), NotifyError>;
+}
+
+/// How long the notifiers wait for the server or the command, so a hanging one doesn't block the
+/// workers
+const NOTIFY_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Builds the client of the HTTP notifiers
+fn http_client() -> reqwest::Client {
+    reqwest::Client::builder()
+        .timeout(NOTIFY_TIMEOUT)
+        .build()
+        .expect("Failed to build the HTTP client")
+}
+
+/// Builds the notifier with the given name (see [`crate::config::NotifyRoute`])
+fn build_notifier(
+    config: &Config,
+    account: &AccountConfig,
+    name: &str,
+    bot: Option<&Arc<Bot>>,
+) -> Result<Arc<dyn Notifier>, NotifyError> {
+    let unavailable = || NotifyError::Unavailable(name.to_owned());
+    if name == "telegram" {
+        let bot = bot.ok_or_else(unavailable)?;
+        let chat_id = account.chat_id.ok_or_else(unavailable)?;
+        return Ok(Arc::new(TelegramNotifier::new(Arc::clone(bot), chat_id)));
+    }
+    if name == "stdout" {
+        return Ok(Arc::new(LogNotifier::new(LogConfig::default())));
+    }
+
+    let notifiers = &config.notifiers;
+    let (kind, key) = name.split_once('.').ok_or_else(unavailable)?;
+    let notifier: Arc<dyn Notifier> = match kind {
+        "webhook" => Arc::new(WebhookNotifier::new(
+            notifiers.webhook.get(key).ok_or_else(unavailable)?.clone(),
+        )),
+        #[cfg(feature = "email")]
+        "email" => Arc::new(EmailNotifier::new(
+            name,
+            notifiers.email.get(key).ok_or_else(unavailable)?,
+        )?),
+        #[cfg(not(feature = "email"))]
+        "email" => {
+            return Err(NotifyError::InvalidConfig(
+                name.to_owned(),
+                "easycar was built without the email feature".to_owned(),
+            ))
+        }
+        "ntfy" => Arc::new(NtfyNotifier::new(
+            notifiers.ntfy.get(key).ok_or_else(unavailable)?.clone(),
+        )),
+        "matrix" => Arc::new(MatrixNotifier::new(
+            name,
+            notifiers.matrix.get(key).ok_or_else(unavailable)?.clone(),
+        )?),
+        "command" => Arc::new(CommandNotifier::new(
+            notifiers.command.get(key).ok_or_else(unavailable)?.clone(),
+        )),
+        "log" => Arc::new(LogNotifier::new(
+            notifiers.log.get(key).ok_or_else(unavailable)?.clone(),
+        )),
+        _ => return Err(unavailable()),
+    };
+    Ok(notifier)
+}
+
+struct Route {
+    route: NotifyRoute,
+    notifier: Arc<dyn Notifier>,
+}
+
+/// Sends the notifications of an account to the notifiers chosen in its configuration
+#[derive(Default)]
+pub struct NotificationRouter {
+    routes: Vec<Route>,
+}
+
+impl NotificationRouter {
+    /// Builds the notifiers of the account. Telegram is only available if the `bot` is given.
+    pub fn new(
+        config: &Config,
+        account: &AccountConfig,
+        bot: Option<&Arc<Bot>>,
+    ) -> Result<Self, NotifyError> {
+        let routes = config
+            .routes(account)
+            .into_iter()
+            .map(|route| {
+                Ok(Route {
+                    notifier: build_notifier(config, account, &route.notifier, bot)?,
+                    route,
+                })
+            })
+            .collect::<Result<_, NotifyError>>()?;
+        Ok(Self { routes })
+    }
+
+    /// Sends the notification to every notifier which accepts its event. Fails only if none of
+    /// them succeeded.
+    pub async fn send(&self, notification: &Notification) -> Result<(), NotifyError> {
+        let mut delivered = false;
+        let mut last_error = None;
+        for route in self
+            .routes
+            .iter()
+            .filter(|route| route.route.accepts(notification.event))
+        {
+            let result = route.notifier.notify(notification).await;
+            counter!(
+                "easycar_notifications_total",
+                "account" => notification.account.clone(),
+                "notifier" => route.route.notifier.clone(),
+                "event" => notification.event.to_string(),
+                "result" => if result.is_ok() { "ok" } else { "error" },
+            )
+            .increment(1);
+            match result {
+                Ok(()) => delivered = true,
+                Err(err) => {
+                    log::error!(
+                        "[{}] Failed to send the {} notification with {}: {err}",
+                        notification.account,
+                        notification.event,
+                        route.route.notifier
+                    );
+                    last_error = Some(err);
+                }
+            }
+        }
+        match last_error {
+            Some(err) if !delivered => Err(err),
+            _ => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,45 @@
+use async_trait::async_trait;
+
+use super::{http_client, Event, Notification, Notifier, NotifyError};
+use crate::config::NtfyConfig;
+
+/// Publishes the notifications to an ntfy topic
+pub struct NtfyNotifier {
+    client: reqwest::Client,
+    config: NtfyConfig,
+}
+
+impl NtfyNotifier {
+    pub fn new(config: NtfyConfig) -> Self {
+        Self {
+            client: http_client(),
+            config,
+        }
+    }
+}
+
+#[async_trait]
+impl Notifier for NtfyNotifier {
+    async fn notify(&self, notification: &Notification) -> Result<(), NotifyError> {
+        let priority = match notification.event {
+            Event::NewExam | Event::Payment | Event::Error => "high",
+            _ => "default",
+        };
+        let mut request = self
+            .client
+            .post(format!(
+                "{}/{}",
+                self.config.server.trim_end_matches('/'),
+                self.config.topic
+            ))
+            .header("Title", notification.title())
+            .header("Tags", notification.event.to_string())
+            .header("Priority", priority)
+            .body(notification.text());
+        if let Some(token) = &self.config.token {
+            request = request.bearer_auth(token);
+        }
+        request.send().await?.error_for_status()?;
+        Ok(())
+    }
+}
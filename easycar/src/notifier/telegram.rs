@@ -0,0 +1,57 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use metrics::counter;
+use teloxide::{prelude::*, types::ParseMode};
+
+use super::{Notification, Notifier, NotifyError};
+
+/// Sends the notifications to the telegram chat of the account
+pub struct TelegramNotifier {
+    bot: Arc<Bot>,
+    chat_id: ChatId,
+}
+
+impl TelegramNotifier {
+    pub fn new(bot: Arc<Bot>, chat_id: i64) -> Self {
+        Self {
+            bot,
+            chat_id: ChatId(chat_id),
+        }
+    }
+}
+
+#[async_trait]
+impl Notifier for TelegramNotifier {
+    async fn notify(&self, notification: &Notification) -> Result<(), NotifyError> {
+        self.send(notification).await.inspect_err(|_| {
+            counter!("easycar_telegram_errors_total", "source" => "notifier").increment(1);
+        })
+    }
+}
+
+impl TelegramNotifier {
+    async fn send(&self, notification: &Notification) -> Result<(), NotifyError> {
+        let request = self
+            .bot
+            .send_message(self.chat_id, &notification.html)
+            .parse_mode(ParseMode::Html);
+        match &notification.keyboard {
+            Some(keyboard) => request.reply_markup(keyboard.clone()).await?,
+            None => request.await?,
+        };
+
+        if let Some(venue) = &notification.venue {
+            self.bot
+                .send_venue(
+                    self.chat_id,
+                    venue.latitude,
+                    venue.longitude,
+                    &venue.title,
+                    &venue.address,
+                )
+                .await?;
+        }
+        Ok(())
+    }
+}
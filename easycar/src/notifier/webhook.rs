@@ -0,0 +1,38 @@
+use async_trait::async_trait;
+use serde_json::json;
+
+use super::{http_client, Notification, Notifier, NotifyError};
+use crate::config::WebhookConfig;
+
+/// Posts the notifications as JSON to an HTTP endpoint
+pub struct WebhookNotifier {
+    client: reqwest::Client,
+    config: WebhookConfig,
+}
+
+impl WebhookNotifier {
+    pub fn new(config: WebhookConfig) -> Self {
+        Self {
+            client: http_client(),
+            config,
+        }
+    }
+}
+
+#[async_trait]
+impl Notifier for WebhookNotifier {
+    async fn notify(&self, notification: &Notification) -> Result<(), NotifyError> {
+        let mut request = self.client.post(&self.config.url).json(&json!({
+            "event": notification.event,
+            "account": notification.account,
+            "title": notification.title(),
+            "text": notification.text(),
+            "html": notification.html,
+        }));
+        for (name, value) in &self.config.headers {
+            request = request.header(name, value);
+        }
+        request.send().await?.error_for_status()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,144 @@
+use std::{fmt, num::NonZeroU32};
+
+use teloxide::types::{InlineKeyboardButton, InlineKeyboardMarkup};
+
+use crate::{
+    i18n::{Language, Text},
+    service::pages::ReservationFilter,
+};
+
+/// Actions attached to the inline keyboard buttons
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum CallbackAction {
+    /// Ask for a BLIK code to pay for the reservation
+    Pay(String),
+    /// Add the WORD center to the watch list
+    WatchWord(NonZeroU32),
+    /// Show another page of the reservations
+    ReservationsPage(ReservationFilter, usize),
+    /// Show another page of the exams
+    ExamsPage(usize),
+}
+
+impl CallbackAction {
+    pub fn parse(data: &str) -> Option<Self> {
+        let (action, argument) = data.split_once(':')?;
+        match action {
+            "pay" => Some(CallbackAction::Pay(argument.to_owned())),
+            "watch" => argument.parse().ok().map(CallbackAction::WatchWord),
+            "reservations" => {
+                let (filter, page) = argument.split_once(':')?;
+                Some(CallbackAction::ReservationsPage(
+                    filter.parse().ok()?,
+                    page.parse().ok()?,
+                ))
+            }
+            "exams" => argument.parse().ok().map(CallbackAction::ExamsPage),
+            _ => None,
+        }
+    }
+
+    pub fn button(self, text: impl Into<String>) -> InlineKeyboardButton {
+        InlineKeyboardButton::callback(text, self.to_string())
+    }
+}
+
+impl fmt::Display for CallbackAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CallbackAction::Pay(reservation_id) => write!(f, "pay:{reservation_id}"),
+            CallbackAction::WatchWord(word_id) => write!(f, "watch:{word_id}"),
+            CallbackAction::ReservationsPage(filter, page) => {
+                write!(f, "reservations:{filter}:{page}")
+            }
+            CallbackAction::ExamsPage(page) => write!(f, "exams:{page}"),
+        }
+    }
+}
+
+/// Text of the message asking for a BLIK code, which ends with the reservation id
+pub fn pay_prompt(language: Language, reservation_id: &str) -> String {
+    format!("{}{reservation_id}", language.text(Text::PayPrompt))
+}
+
+pub fn pay_keyboard(reservation_id: String, language: Language) -> InlineKeyboardMarkup {
+    InlineKeyboardMarkup::new([[
+        CallbackAction::Pay(reservation_id).button(language.text(Text::PayButton))
+    ]])
+}
+
+/// Returns the reservation id if the text is a [`pay_prompt`] in any language
+pub fn parse_pay_prompt(text: &str) -> Option<&str> {
+    Language::ALL
+        .into_iter()
+        .find_map(|language| text.strip_prefix(language.text(Text::PayPrompt)))
+        .map(str::trim)
+        .filter(|id| !id.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Telegram rejects buttons with a longer `callback_data`
+    const CALLBACK_DATA_LIMIT: usize = 64;
+
+    fn actions() -> Vec<CallbackAction> {
+        vec![
+            CallbackAction::Pay("8c7f3a52-1f4e-4b7a-9a0c-2d6e5b1f9e47".to_owned()),
+            CallbackAction::WatchWord(NonZeroU32::MAX),
+            CallbackAction::ReservationsPage(ReservationFilter::All, 0),
+            CallbackAction::ReservationsPage(ReservationFilter::Active, 3),
+            CallbackAction::ReservationsPage(ReservationFilter::Cancelled, usize::MAX),
+            CallbackAction::ExamsPage(usize::MAX),
+        ]
+    }
+
+    #[test]
+    fn round_trips() {
+        for action in actions() {
+            assert_eq!(
+                CallbackAction::parse(&action.to_string()),
+                Some(action.clone()),
+                "{action}"
+            );
+        }
+    }
+
+    #[test]
+    fn fits_callback_data_limit() {
+        for action in actions() {
+            let data = action.to_string();
+            assert!(data.len() <= CALLBACK_DATA_LIMIT, "{data}");
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_data() {
+        for data in [
+            "",
+            "pay",
+            "watch:0",
+            "watch:abc",
+            "exams:",
+            "exams:-1",
+            "reservations:all",
+            "reservations:paid:1",
+            "unknown:1",
+        ] {
+            assert_eq!(CallbackAction::parse(data), None, "{data}");
+        }
+    }
+
+    #[test]
+    fn parses_pay_prompts() {
+        for language in Language::ALL {
+            assert_eq!(
+                parse_pay_prompt(&pay_prompt(language, "abc-123")),
+                Some("abc-123")
+            );
+        }
+        assert_eq!(parse_pay_prompt(&pay_prompt(Language::default(), "")), None);
+        assert_eq!(parse_pay_prompt("abc-123"), None);
+    }
+}
@@ -1,25 +1,50 @@
+pub mod callbacks;
+pub mod pages;
+pub mod progress;
+pub mod reload;
+pub mod settings;
+pub mod supervisor;
+#[cfg(feature = "webhook")]
+pub mod webhook;
 pub mod workers;
 
 use std::error::Error;
 use std::fmt::Write;
-use std::num::ParseIntError;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::path::PathBuf;
 use std::sync::Arc;
 
-use crate::client::{GetExamsError, InfoCarClient, NewClientError, UserData};
-use crate::utils::{date_from_string, readable_date_from_string, readable_time_delta};
+use crate::account::{find_account, Account};
+use crate::client::{ActiveReservationError, GetExamsError, InfoCarClient, NewClientError, Step};
+use crate::config::Config;
+use crate::health::HealthReport;
+use crate::i18n::{Language, Text};
+use crate::notifier::{Event, Notification, NotifyError};
+use crate::state::{StateError, StateStore};
+use crate::utils::{parse_duration, short_duration, try_date_from_string};
+use callbacks::{parse_pay_prompt, pay_keyboard, pay_prompt, CallbackAction};
 use chrono::{DateTime, Utc};
 use info_car_api::error::{EnrollError, GenericClientError};
-use info_car_api::types::ProfileIdType;
+use info_car_api::types::{PossibleStatuses, ReservationStatus};
+use metrics::counter;
+use pages::ReservationFilter;
+use progress::{retry_after, Progress};
+use reload::{config_watcher, SharedConfig};
+use supervisor::Supervisor;
 use teloxide::payloads::SetChatMenuButtonSetters;
-use teloxide::types::{MenuButton, MessageId, ParseMode};
+use teloxide::types::{
+    BotCommand, ForceReply, InlineKeyboardMarkup, InputFile, MenuButton, ParseMode,
+};
 use teloxide::RequestError;
-use teloxide::{prelude::*, utils::command::BotCommands};
-use thiserror::Error;
-use tokio::sync::{
-    oneshot::{self, Receiver},
-    Mutex,
+use teloxide::{
+    prelude::*,
+    utils::{command::BotCommands, html},
 };
-use tokio::time::{sleep, Duration};
+use thiserror::Error;
+use tokio::sync::Mutex;
+use tokio::time::{timeout_at, Instant};
+use tokio_util::sync::CancellationToken;
 use workers::*;
 
 /// These commands are supported:
@@ -35,15 +60,18 @@ enum Command {
     /// Gets current chat id
     #[command()]
     ChatId,
+    /// Show whether the watchers and the session work
+    #[command()]
+    Health,
     /// Get exam dates
     #[command()]
     Exams,
     /// Get current exam
     #[command()]
     Exam,
-    /// Show all reservations
+    /// Show the reservations, optionally only the active or cancelled ones (e.g. /reservations active)
     #[command()]
-    Reservations,
+    Reservations(String),
     /// Enroll to the exam
     #[command()]
     Enroll(String),
@@ -56,6 +84,93 @@ enum Command {
     /// Cancel reservation
     #[command()]
     Cancel(String),
+    /// Export active reservations (every one with "all", or free exams with "exams") to a calendar file
+    #[command()]
+    Calendar(String),
+    /// Show or set when to remind about the exam (e.g. /reminders 7d 1d 2h)
+    #[command()]
+    Reminders(String),
+    /// Reload the configuration file
+    #[command()]
+    Reload,
+    /// Show the effective configuration
+    #[command()]
+    Settings,
+    /// Show or change the watched WORDs (/watch add 3, /watch remove 3, /watch reset)
+    #[command()]
+    Watch(String),
+    /// Show or set the watched categories (e.g. /category B C)
+    #[command()]
+    Category(String),
+    /// Show or set how many days ahead to look for exams (e.g. /horizon 60)
+    #[command()]
+    Horizon(String),
+    /// Show or change the exam filter (e.g. /filter weekdays mon fri, /filter max_price, /filter clear)
+    #[command()]
+    Filter(String),
+    /// Search the WORD centers by name, address or province (e.g. /words gdansk)
+    #[command()]
+    Words(String),
+    /// Show or change the language of the bot (/language en, /language pl, /language reset)
+    #[command()]
+    Language(String),
+}
+
+/// The Polish description of the command, which starts with a slash
+fn polish_description(command: &str) -> Option<&'static str> {
+    Some(match command {
+        "/help" => "Wyświetl tę wiadomość",
+        "/uptime" => "Pokaż czas działania bota",
+        "/chatid" => "Pokaż identyfikator tego czatu",
+        "/health" => "Pokaż, czy sprawdzanie w tle i sesja działają",
+        "/exams" => "Pokaż terminy egzaminów",
+        "/exam" => "Pokaż aktualny egzamin",
+        "/reservations" => "Pokaż rezerwacje, opcjonalnie tylko aktywne lub anulowane (np. /reservations aktywne)",
+        "/enroll" => "Zapisz się na egzamin",
+        "/status" => "Pokaż status rezerwacji",
+        "/pay" => "Zapłać za egzamin kodem BLIK (identyfikator rezerwacji i kod BLIK oddzielone spacją)",
+        "/cancel" => "Anuluj rezerwację",
+        "/calendar" => "Eksportuj aktywne rezerwacje (wszystkie z \"all\", lub wolne terminy z \"exams\") do pliku kalendarza",
+        "/reminders" => "Pokaż lub ustaw, kiedy przypominać o egzaminie (np. /reminders 7d 1d 2h)",
+        "/reload" => "Przeładuj plik konfiguracji",
+        "/settings" => "Pokaż aktualną konfigurację",
+        "/watch" => "Pokaż lub zmień obserwowane WORD-y (/watch add 3, /watch remove 3, /watch reset)",
+        "/category" => "Pokaż lub ustaw obserwowane kategorie (np. /category B C)",
+        "/horizon" => "Pokaż lub ustaw, ile dni naprzód szukać egzaminów (np. /horizon 60)",
+        "/filter" => "Pokaż lub zmień filtr egzaminów (np. /filter weekdays mon fri, /filter max_price, /filter clear)",
+        "/words" => "Szukaj ośrodków WORD po nazwie, adresie lub województwie (np. /words gdansk)",
+        "/language" => "Pokaż lub zmień język bota (/language en, /language pl, /language reset)",
+        _ => return None,
+    })
+}
+
+/// The commands with the descriptions in the `language`
+fn bot_commands(language: Language) -> Vec<BotCommand> {
+    Command::bot_commands()
+        .into_iter()
+        .map(|command| match language {
+            Language::English => command,
+            Language::Polish => {
+                let description = polish_description(&command.command)
+                    .map(ToOwned::to_owned)
+                    .unwrap_or(command.description);
+                BotCommand::new(command.command, description)
+            }
+        })
+        .collect()
+}
+
+fn help(language: Language) -> String {
+    match language {
+        Language::English => Command::descriptions().to_string(),
+        Language::Polish => bot_commands(language).into_iter().fold(
+            language.text(Text::HelpHeader).to_owned(),
+            |mut output, command| {
+                let _ = write!(output, "\n{} — {}", command.command, command.description);
+                output
+            },
+        ),
+    }
 }
 
 #[derive(Debug, Error)]
@@ -67,107 +182,239 @@ pub enum AnswerError {
     #[error(transparent)]
     EnrollToExamError(#[from] EnrollError),
     #[error(transparent)]
+    ActiveReservationError(#[from] ActiveReservationError),
+    #[error(transparent)]
     TeloxideError(#[from] RequestError),
     #[error("Too few arguments! Expected: {0}, got {1}!")]
     TooFewArguments(u32, u32),
+    #[error("Invalid argument: {0}")]
+    InvalidArgument(String),
+    #[error(transparent)]
+    StateError(#[from] StateError),
+    /// A command answered right away was passed to [`handle_progress_cmd`]
+    #[error("This command doesn't show its progress")]
+    NotAProgressCommand,
 }
 
-async fn waiting_spinner(
-    mut rx: Receiver<()>,
-    bot: Arc<Bot>,
-    chat_id: ChatId,
-) -> Result<MessageId, RequestError> {
-    let spinner = ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
-    let mut iter = spinner.iter().cycle();
+impl AnswerError {
+    /// Describes the error to the user
+    fn localized(&self, language: Language) -> String {
+        match self {
+            AnswerError::TooFewArguments(expected, got) => language.format(
+                Text::TooFewArguments,
+                &[("expected", expected), ("got", got)],
+            ),
+            AnswerError::InvalidArgument(message) => {
+                language.format(Text::InvalidArgument, &[("message", message)])
+            }
+            err => err.to_string(),
+        }
+    }
+}
+
+/// Renders the `error` template, which is HTML
+fn error_message(error: &str, language: Language, config: &Config) -> String {
+    config
+        .templates
+        .render(Text::Error, language, &[("error", &html::escape(error))])
+}
+
+/// A response to a command
+pub struct Reply {
+    text: String,
+    keyboard: Option<InlineKeyboardMarkup>,
+    document: Option<InputFile>,
+}
 
-    // Send an initial message and get its MessageId to edit later
-    let sent_message = bot.send_message(chat_id, *iter.next().unwrap()).await?;
+impl Reply {
+    fn with_keyboard(text: String, keyboard: InlineKeyboardMarkup) -> Self {
+        Self {
+            text,
+            keyboard: Some(keyboard),
+            document: None,
+        }
+    }
 
-    // Simulate the spinner by continuously editing the message
-    for frame in iter {
-        if rx.try_recv().is_ok() {
-            break; // Stop the spinner when the signal is received
+    fn with_document(text: String, document: InputFile) -> Self {
+        Self {
+            text,
+            keyboard: None,
+            document: Some(document),
         }
-        bot.edit_message_text(chat_id, sent_message.id, *frame)
-            .await?;
-        sleep(Duration::from_millis(100)).await; // Adjust the speed here
     }
-    Ok(sent_message.id)
 }
 
-async fn handle_spinner_cmd(
+impl From<String> for Reply {
+    fn from(text: String) -> Self {
+        Self {
+            text,
+            keyboard: None,
+            document: None,
+        }
+    }
+}
+
+fn payment_state(status: &ReservationStatus, language: Language) -> String {
+    match (&status.status.status, &status.active_payment) {
+        (PossibleStatuses::SignupConfirmed, _) => language.text(Text::Paid).to_owned(),
+        (_, Some(payment)) => language.format(
+            Text::PaymentInProgress,
+            &[("payment", &html::escape(payment))],
+        ),
+        _ => language.text(Text::NotPaid).to_owned(),
+    }
+}
+
+pub(crate) fn format_current_exam(status: ReservationStatus, language: Language) -> String {
+    let payment = payment_state(&status, language);
+    let exam = status.exam;
+    let countdown = try_date_from_string(&exam.exam_date)
+        .map(|date| language.time_delta(date.signed_duration_since(Utc::now())))
+        .unwrap_or(language.text(Text::Unknown).to_owned());
+    let room = exam
+        .theory
+        .as_ref()
+        .or(exam.pracitce.as_ref())
+        .and_then(|v| v.room.as_deref().map(html::escape))
+        .unwrap_or(language.text(Text::RoomNotAssigned).to_owned());
+    let vehicle = exam
+        .osk_vehicle_number
+        .as_deref()
+        .map(html::escape)
+        .unwrap_or(language.text(Text::None).to_owned());
+
+    language.format(
+        Text::CurrentExam,
+        &[
+            ("id", &status.id),
+            ("word", &html::escape(&exam.organization_unit_name)),
+            ("address", &html::escape(&exam.address)),
+            ("date", &language.date_from_string(&exam.exam_date)),
+            ("countdown", &countdown),
+            ("room", &room),
+            ("vehicle", &vehicle),
+            ("payment", &payment),
+        ],
+    )
+}
+
+/// Logs in again if the session expired, e.g. after failed refreshes
+async fn ensure_session(client: &mut InfoCarClient, account: &Account, progress: &Progress) {
+    if client
+        .get_token_expire_date()
+        .is_some_and(|expires| expires > Utc::now())
+    {
+        return;
+    }
+    progress.step(progress.language().text(Text::LoggingIn));
+    match client.refresh_token().await {
+        Ok(expires) => account.health.token_refreshed(expires),
+        Err(err) => log::error!("[{}] Failed to log in: {err}", account.name),
+    }
+}
+
+async fn handle_progress_cmd(
     cmd: Command,
-    client: Arc<Mutex<InfoCarClient>>,
-) -> Result<String, AnswerError> {
+    account: &Account,
+    config: &Config,
+    progress: &Progress,
+) -> Result<Reply, AnswerError> {
+    let language = progress.language();
+    let mut client = progress.lock(&account.client).await;
+    ensure_session(&mut client, account, progress).await;
+    let on_step = |step: Step| progress.step(step.describe(language));
+    let step = |text: Text, id: &str| progress.step(language.format(text, &[("id", &id)]));
     match cmd {
         Command::Exams => {
-            let exams = client.lock().await.get_nearest_exams(5).await?;
-            Ok(format!(
-                "The available exams are:\n{}",
-                exams.into_iter().fold(String::new(), |mut output, exam| {
-                    let exam_in = date_from_string(&exam.date)
-                        .signed_duration_since(Utc::now())
-                        .num_days();
-                    let readable_date = readable_date_from_string(exam.date);
-                    let _ = writeln!(
-                        output,
-                        "Exam (<code>{}</code>): {} (in <b>{}</b> days)",
-                        exam.id, readable_date, exam_in,
-                    );
-                    output
-                })
-            ))
+            pages::exams(&mut client, &account.pages, true, 0, language, &on_step).await
         }
-        Command::Reservations => {
-            let reservations = client.lock().await.get_reservations().await?;
+        Command::Exam => {
+            progress.step(language.text(Text::FetchingActiveReservation));
+            let status = client.get_active_reservation().await?;
 
-            let text: String = reservations
-                .items
-                .iter()
-                .fold(String::new(), |mut output, v| {
-                    let _ = write!(
-                        output,
-                        "• At {} in {} ({})\n\n",
-                        v.exam
-                            .practice
-                            .as_ref()
-                            .or(v.exam.theory.as_ref())
-                            .unwrap()
-                            .date,
-                        v.exam.organization_unit_name,
-                        v.status.status
-                    );
-                    output
-                });
-            Ok(text)
+            Ok(status
+                .map(|status| format_current_exam(status, language))
+                .unwrap_or(language.text(Text::NoActiveExam).to_owned())
+                .into())
+        }
+        Command::Reservations(filter) => {
+            let filter = ReservationFilter::parse(&filter, language)?;
+            progress.step(language.text(Text::FetchingReservations));
+            pages::reservations(&mut client, &account.pages, true, filter, 0, language).await
+        }
+        Command::Calendar(kind) => {
+            let (calendar, file_name) = match kind.trim() {
+                "exams" => (client.exams_calendar(&on_step).await?, "exams.ics"),
+                "" | "reservations" => {
+                    progress.step(language.text(Text::FetchingReservations));
+                    (client.reservations_calendar().await?, "reservations.ics")
+                }
+                "all" => {
+                    progress.step(language.text(Text::FetchingReservations));
+                    (
+                        client.reservation_list_calendar().await?,
+                        "all-reservations.ics",
+                    )
+                }
+                other => Err(AnswerError::InvalidArgument(other.to_owned()))?,
+            };
+
+            Ok(Reply::with_document(
+                language.format(Text::ExportedEvents, &[("count", &calendar.events.len())]),
+                InputFile::memory(calendar.to_string()).file_name(file_name),
+            ))
         }
         Command::Enroll(exam_id) => {
-            let reservation_id = client.lock().await.enroll(exam_id).await?;
+            step(Text::Enrolling, &exam_id);
+            let reservation_id = client.enroll(exam_id.clone()).await?;
+            let deadline = account.record_enrollment(&reservation_id, config).await;
 
-            Ok(format!(
-                "Enrolled to the exam! The reservation id is <code>{reservation_id}</code>\nCheck the status using <code>/status {reservation_id}</code>"
+            Ok(Reply::with_keyboard(
+                config.templates.render(
+                    Text::Enrolled,
+                    language,
+                    &[
+                        ("reservation_id", &reservation_id),
+                        ("exam_id", &exam_id),
+                        ("deadline", &language.date(deadline)),
+                    ],
+                ),
+                pay_keyboard(reservation_id, language),
             ))
         }
         Command::Status(reservation_id) => {
-            let status = client.lock().await.status(reservation_id).await?;
-
-            Ok(format!(
-                "ID: {}\nStatus: {}\nReason: {}\nWord: {}\nAddress: {}\nCategory: {}\nDate: {}",
-                status.id,
-                status.status.status,
-                status.status.message.unwrap_or("None".to_string()),
-                status.exam.organization_unit_name,
-                status.exam.address,
-                status.exam.category,
-                status.exam.exam_date,
-            ))
+            step(Text::FetchingStatus, &reservation_id);
+            let status = client.status(reservation_id).await?;
+
+            Ok(language
+                .format(
+                    Text::ReservationStatus,
+                    &[
+                        ("id", &status.id),
+                        ("status", &language.status(&status.status.status)),
+                        (
+                            "reason",
+                            &status
+                                .status
+                                .message
+                                .map(|message| html::escape(&message))
+                                .unwrap_or(language.text(Text::None).to_owned()),
+                        ),
+                        ("word", &html::escape(&status.exam.organization_unit_name)),
+                        ("address", &html::escape(&status.exam.address)),
+                        ("category", &status.exam.category),
+                        ("date", &language.date_from_string(&status.exam.exam_date)),
+                    ],
+                )
+                .into())
         }
         Command::Cancel(reservation_id) => {
-            client.lock().await.cancel(reservation_id.clone()).await?;
+            step(Text::Cancelling, &reservation_id);
+            client.cancel(reservation_id.clone()).await?;
 
-            Ok(format!(
-                "Successfully canceled reservation: {reservation_id}",
-            ))
+            Ok(language
+                .format(Text::ReservationCancelled, &[("id", &reservation_id)])
+                .into())
         }
         Command::Pay(commands) => {
             let mut commands = commands.split_whitespace();
@@ -181,172 +428,826 @@ async fn handle_spinner_cmd(
                 .ok_or(AnswerError::TooFewArguments(2, 1))?
                 .to_string();
 
-            let response = client
-                .lock()
-                .await
-                .pay(reservation_id.clone(), blik_code)
-                .await?;
+            step(Text::Paying, &reservation_id);
+            let response = client.pay(reservation_id.clone(), blik_code).await?;
 
-            Ok(format!(
-                "Paid for exam {reservation_id} with {:.2} PLN.\nStatus: {}",
-                response.paid_amount as f64 / 100.0,
-                response.payment_status
-            ))
+            Ok(language
+                .format(
+                    Text::PaidForExam,
+                    &[
+                        ("id", &reservation_id),
+                        (
+                            "amount",
+                            &format!("{:.2}", response.paid_amount as f64 / 100.0),
+                        ),
+                        ("status", &response.payment_status),
+                    ],
+                )
+                .into())
+        }
+        Command::Words(query) => {
+            if query.trim().is_empty() {
+                return Err(AnswerError::InvalidArgument(
+                    language.text(Text::WordsQueryMissing).to_owned(),
+                ));
+            }
+            progress.step(language.text(Text::SearchingWords));
+            let words = client.search_words(&query, 5).await?;
+            if words.is_empty() {
+                return Ok(language
+                    .format(Text::NoWordsMatch, &[("query", &html::escape(&query))])
+                    .into());
+            }
+
+            let text = words.iter().fold(
+                language.text(Text::WordsHeader).to_owned(),
+                |mut output, info| {
+                    let flag = |value: Option<bool>| {
+                        language.text(match value {
+                            Some(true) => Text::Yes,
+                            Some(false) => Text::No,
+                            None => Text::UnknownLowercase,
+                        })
+                    };
+                    let _ = write!(
+                        output,
+                        "\n\n<b>{}</b> (ID: <code>{}</code>)\n{}{}\n{}",
+                        html::escape(&info.word.name),
+                        info.word.id,
+                        html::escape(&info.word.address),
+                        info.province
+                            .as_ref()
+                            .map(|province| format!(", {}", html::escape(province)))
+                            .unwrap_or_default(),
+                        language.format(
+                            Text::WordDetails,
+                            &[
+                                ("offline", &flag(Some(info.word.offline))),
+                                ("reschedule", &flag(info.reschedule_enabled)),
+                            ],
+                        ),
+                    );
+                    output
+                },
+            );
+            let keyboard = InlineKeyboardMarkup::new(words.iter().map(|info| {
+                [CallbackAction::WatchWord(info.word.id)
+                    .button(language.format(Text::WatchWordButton, &[("word", &info.word.name)]))]
+            }));
+            Ok(Reply::with_keyboard(text, keyboard))
+        }
+        Command::Help
+        | Command::Uptime
+        | Command::ChatId
+        | Command::Health
+        | Command::Reminders(_)
+        | Command::Reload
+        | Command::Settings
+        | Command::Watch(_)
+        | Command::Category(_)
+        | Command::Horizon(_)
+        | Command::Filter(_)
+        | Command::Language(_) => Err(AnswerError::NotAProgressCommand),
+    }
+}
+
+/// Handles a slow command, showing its progress in a status message, which is then replaced with
+/// the reply
+async fn answer_with_progress(
+    bot: Arc<Bot>,
+    chat_id: ChatId,
+    cmd: Command,
+    account: Arc<Account>,
+    config: SharedConfig,
+) -> Result<(), AnswerError> {
+    let language = account.language();
+    let progress = Progress::start(Arc::clone(&bot), chat_id, language);
+    let resp = handle_progress_cmd(cmd, &account, &config.current(), &progress).await;
+    let message_id = progress.finish().await;
+
+    let (text, keyboard) = match &resp {
+        Ok(reply) => (reply.text.clone(), reply.keyboard.clone()),
+        Err(err) => (
+            error_message(&err.localized(language), language, &config.current()),
+            None,
+        ),
+    };
+    retry_after(|| {
+        let (bot, text, keyboard) = (Arc::clone(&bot), text.clone(), keyboard.clone());
+        async move {
+            match message_id {
+                Some(message_id) => {
+                    let request = bot
+                        .edit_message_text(chat_id, message_id, text)
+                        .parse_mode(ParseMode::Html);
+                    match keyboard {
+                        Some(keyboard) => request.reply_markup(keyboard).await,
+                        None => request.await,
+                    }
+                }
+                // The status message couldn't be sent
+                None => {
+                    let request = bot.send_message(chat_id, text).parse_mode(ParseMode::Html);
+                    match keyboard {
+                        Some(keyboard) => request.reply_markup(keyboard).await,
+                        None => request.await,
+                    }
+                }
+            }
         }
-        _ => unreachable!(),
+    })
+    .await?;
+
+    if let Some(document) = resp?.document {
+        retry_after(|| bot.send_document(chat_id, document.clone()).send()).await?;
     }
+    Ok(())
 }
 
 async fn answer(
     bot: Arc<Bot>,
     msg: Message,
     cmd: Command,
-    client: Arc<Mutex<InfoCarClient>>,
+    accounts: Arc<Vec<Arc<Account>>>,
+    config: SharedConfig,
     start_date: DateTime<Utc>,
 ) -> Result<(), AnswerError> {
-    match cmd {
-        Command::Help => {
-            bot.send_message(msg.chat.id, Command::descriptions().to_string())
-                .await?;
+    let account = find_account(&accounts, msg.chat.id);
+    // Chats which aren't linked to an account get the language of the telegram user
+    let language = match &account {
+        Some(account) => account.language(),
+        None => msg
+            .from
+            .as_ref()
+            .and_then(|user| user.language_code.as_deref())
+            .and_then(|code| code.parse().ok())
+            .unwrap_or_default(),
+    };
+    match (cmd, account) {
+        (Command::Help, _) => {
+            bot.send_message(msg.chat.id, help(language)).await?;
         }
-        Command::Uptime => {
+        (Command::Uptime, _) => {
             bot.send_message(
                 msg.chat.id,
-                format!(
-                    "The uptime is: {}",
-                    readable_time_delta(Utc::now() - start_date)
+                language.format(
+                    Text::Uptime,
+                    &[("uptime", &language.time_delta(Utc::now() - start_date))],
                 ),
             )
             .await?;
         }
-        Command::ChatId => {
+        (Command::ChatId, _) => {
             bot.send_message(
                 msg.chat.id,
-                format!("The current ChatId is: <code>{}</code>", msg.chat.id),
+                language.format(Text::ChatId, &[("chat_id", &msg.chat.id)]),
             )
             .parse_mode(ParseMode::Html)
             .await?;
         }
-        Command::Exam => {
-            bot.send_message(msg.chat.id, "The current exam is: ")
+        (_, None) => {
+            bot.send_message(
+                msg.chat.id,
+                language.format(Text::UnlinkedChat, &[("chat_id", &msg.chat.id)]),
+            )
+            .parse_mode(ParseMode::Html)
+            .await?;
+        }
+        (Command::Reminders(offsets), Some(account)) => {
+            let defaults = config.current().reminders.exam_offsets();
+            let text =
+                match set_reminder_offsets(&offsets, &account.state, defaults, language).await {
+                    Ok(offsets) => language.format(
+                        Text::ReminderOffsets,
+                        &[(
+                            "offsets",
+                            &offsets
+                                .into_iter()
+                                .filter_map(chrono::Duration::try_minutes)
+                                .map(short_duration)
+                                .collect::<Vec<_>>()
+                                .join(", "),
+                        )],
+                    ),
+                    Err(err) => error_message(&err, language, &config.current()),
+                };
+            bot.send_message(msg.chat.id, text)
+                .parse_mode(ParseMode::Html)
                 .await?;
         }
-        // Handle spinner for all commands that use it
-        _ => {
-            // Create a oneshot channel to signal when to stop the spinner
-            let (tx, rx) = oneshot::channel();
+        (Command::Health, Some(account)) => {
+            let report = account.health_report(&config.current());
+            bot.send_message(msg.chat.id, format_health(&report, language))
+                .parse_mode(ParseMode::Html)
+                .await?;
+        }
+        (Command::Reload, Some(_)) => {
+            let text = match config.reload(&accounts).await {
+                Ok(()) => language.text(Text::Reloaded).to_owned(),
+                Err(err) => language.format(Text::ReloadFailed, &[("error", &err)]),
+            };
+            bot.send_message(msg.chat.id, text).await?;
+        }
+        (Command::Settings, Some(account)) => {
+            bot.send_message(
+                msg.chat.id,
+                settings::show_settings(&account, &config.current()).await,
+            )
+            .parse_mode(ParseMode::Html)
+            .await?;
+        }
+        (
+            cmd @ (Command::Watch(_)
+            | Command::Category(_)
+            | Command::Horizon(_)
+            | Command::Filter(_)
+            | Command::Language(_)),
+            Some(account),
+        ) => {
+            let result = match cmd {
+                Command::Watch(args) => settings::watch(&args, &account, &config.current()).await,
+                Command::Category(args) => settings::category(&args, &account).await,
+                Command::Horizon(args) => settings::horizon(&args, &account).await,
+                Command::Filter(args) => settings::filter(&args, &account).await,
+                Command::Language(args) => {
+                    settings::language(&args, &account, &config.current()).await
+                }
+                _ => unreachable!(),
+            };
+            // The language may have just been changed
+            let language = account.language();
+            let text = result.unwrap_or_else(|err| {
+                error_message(&err.localized(language), language, &config.current())
+            });
+            bot.send_message(msg.chat.id, text)
+                .parse_mode(ParseMode::Html)
+                .await?;
+        }
+        // Show the progress of the slow commands
+        (
+            cmd @ (Command::Exams
+            | Command::Exam
+            | Command::Reservations(_)
+            | Command::Calendar(_)
+            | Command::Enroll(_)
+            | Command::Status(_)
+            | Command::Cancel(_)
+            | Command::Pay(_)
+            | Command::Words(_)),
+            Some(account),
+        ) => answer_with_progress(bot, msg.chat.id, cmd, account, config).await?,
+    }
+    Ok(())
+}
+
+fn format_health(report: &HealthReport, language: Language) -> String {
+    let date = |date: Option<DateTime<Utc>>| {
+        date.map(|date| language.date(date))
+            .unwrap_or(language.text(Text::Never).to_owned())
+    };
+    let mut text = if report.healthy {
+        language.text(Text::HealthOk).to_owned()
+    } else {
+        format!(
+            "{}\n{}",
+            language.text(Text::HealthProblems),
+            report
+                .problems
+                .iter()
+                .map(|problem| format!("• {}", html::escape(problem)))
+                .collect::<Vec<_>>()
+                .join("\n")
+        )
+    };
+    let _ = write!(
+        text,
+        "\n\n{}",
+        language.format(
+            Text::HealthDates,
+            &[
+                ("last_poll", &date(report.last_poll)),
+                ("last_token_refresh", &date(report.last_token_refresh)),
+                ("token_expires", &date(report.token_expires)),
+            ],
+        )
+    );
+    for (worker, beat) in &report.workers {
+        let _ = write!(
+            text,
+            "\n{}",
+            language.format(
+                Text::HealthWorker,
+                &[("worker", worker), ("date", &language.date(*beat))],
+            )
+        );
+    }
+    for (worker, crash) in &report.crashes {
+        let _ = write!(
+            text,
+            "\n{}",
+            language.format(
+                Text::HealthCrash,
+                &[
+                    ("worker", worker),
+                    ("count", &crash.count),
+                    ("date", &language.date(crash.last)),
+                ],
+            )
+        );
+    }
+    text
+}
+
+/// Saves the space separated reminder offsets. Returns the offsets in effect.
+async fn set_reminder_offsets(
+    offsets: &str,
+    state: &Mutex<StateStore>,
+    defaults: Vec<i64>,
+    language: Language,
+) -> Result<Vec<i64>, String> {
+    let mut state = state.lock().await;
+    if offsets.trim().is_empty() {
+        return Ok(state
+            .state()
+            .exam_reminder_offsets
+            .clone()
+            .unwrap_or(defaults));
+    }
+
+    let mut parsed = offsets
+        .split_whitespace()
+        .map(|offset| {
+            parse_duration(offset)
+                .filter(|duration| *duration > chrono::Duration::zero())
+                .map(|duration| duration.num_minutes())
+                .ok_or_else(|| language.format(Text::InvalidReminderOffset, &[("offset", &offset)]))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    parsed.sort_unstable_by(|a, b| b.cmp(a));
+    parsed.dedup();
 
-            let handle = tokio::spawn(waiting_spinner(rx, Arc::clone(&bot), msg.chat.id));
+    state
+        .update(|state| state.exam_reminder_offsets = Some(parsed.clone()))
+        .await
+        .map_err(|err| err.to_string())?;
+    Ok(parsed)
+}
+
+/// Handles replies to the [`pay_prompt`] messages
+async fn answer_pay_prompt(
+    bot: Arc<Bot>,
+    msg: Message,
+    accounts: Arc<Vec<Arc<Account>>>,
+    config: SharedConfig,
+) -> Result<(), AnswerError> {
+    let Some(account) = find_account(&accounts, msg.chat.id) else {
+        return Ok(());
+    };
+    let Some(reservation_id) = msg
+        .reply_to_message()
+        .and_then(|prompt| prompt.text())
+        .and_then(parse_pay_prompt)
+    else {
+        return Ok(());
+    };
+    let blik_code = msg.text().unwrap_or_default().trim();
+
+    answer_with_progress(
+        bot,
+        msg.chat.id,
+        Command::Pay(format!("{reservation_id} {blik_code}")),
+        account,
+        config,
+    )
+    .await
+}
 
-            let resp = handle_spinner_cmd(cmd, client).await;
+async fn answer_callback(
+    bot: Arc<Bot>,
+    query: CallbackQuery,
+    accounts: Arc<Vec<Arc<Account>>>,
+    config: SharedConfig,
+) -> Result<(), AnswerError> {
+    bot.answer_callback_query(query.id.clone()).await?;
 
-            // Stop the spinner
-            tx.send(()).unwrap();
-            let message_id = handle.await.unwrap()?;
+    let (Some(action), Some(message)) = (
+        query.data.as_deref().and_then(CallbackAction::parse),
+        query.message.as_ref(),
+    ) else {
+        log::warn!("Got an unknown callback query: {:?}", query.data);
+        return Ok(());
+    };
+    let chat_id = message.chat().id;
+    let account = find_account(&accounts, chat_id);
+    let language = account
+        .as_ref()
+        .map(|account| account.language())
+        .unwrap_or_default();
 
-            match resp {
-                Ok(message) => {
-                    bot.edit_message_text(msg.chat.id, message_id, message)
-                        .parse_mode(ParseMode::Html)
-                        .await?;
+    match action {
+        CallbackAction::ReservationsPage(..) | CallbackAction::ExamsPage(_) => {
+            let Some(account) = account else {
+                return Ok(());
+            };
+            let mut client = account.client.lock().await;
+            let reply = match action {
+                CallbackAction::ReservationsPage(filter, page) => {
+                    pages::reservations(&mut client, &account.pages, false, filter, page, language)
+                        .await
                 }
-                Err(err) => {
-                    bot.edit_message_text(msg.chat.id, message_id, format!("❌ Error: {err}"))
-                        .await?;
-                    Err(err)?;
+                CallbackAction::ExamsPage(page) => {
+                    pages::exams(&mut client, &account.pages, false, page, language, &|_| {}).await
                 }
-            }
+                _ => unreachable!(),
+            };
+            drop(client);
+            let (text, keyboard) = match reply {
+                Ok(reply) => (reply.text, reply.keyboard),
+                Err(err) => (
+                    error_message(&err.localized(language), language, &config.current()),
+                    None,
+                ),
+            };
+            let request = bot
+                .edit_message_text(chat_id, message.id(), text)
+                .parse_mode(ParseMode::Html);
+            match keyboard {
+                Some(keyboard) => request.reply_markup(keyboard).await?,
+                None => request.await?,
+            };
+        }
+        CallbackAction::Pay(reservation_id) => {
+            bot.send_message(chat_id, pay_prompt(language, &reservation_id))
+                .reply_markup(
+                    ForceReply::new()
+                        .input_field_placeholder(language.text(Text::BlikPlaceholder).to_owned()),
+                )
+                .await?;
+        }
+        CallbackAction::WatchWord(word_id) => {
+            let Some(account) = account else {
+                return Ok(());
+            };
+            let text = match settings::update_criteria(&account, |criteria| {
+                if !criteria.words.contains(&word_id) {
+                    criteria.words.push(word_id);
+                }
+                Ok(())
+            })
+            .await
+            {
+                Ok(criteria) => language.format(
+                    Text::WordWatched,
+                    &[
+                        ("id", &word_id),
+                        (
+                            "words",
+                            &criteria
+                                .words
+                                .iter()
+                                .map(ToString::to_string)
+                                .collect::<Vec<_>>()
+                                .join(", "),
+                        ),
+                    ],
+                ),
+                Err(err) => error_message(&err.localized(language), language, &config.current()),
+            };
+            bot.send_message(chat_id, text)
+                .parse_mode(ParseMode::Html)
+                .await?;
         }
     }
     Ok(())
 }
 
+/// Logs the errors which are not related to Telegram and passes the rest to the dispatcher
+fn handle_answer_error(result: Result<(), AnswerError>) -> Result<(), RequestError> {
+    match result {
+        Err(AnswerError::TeloxideError(err)) => {
+            counter!("easycar_telegram_errors_total", "source" => "command").increment(1);
+            Err(err)
+        }
+        Err(err) => {
+            log::error!(
+                "{err}{}",
+                err.source()
+                    .map(|src| format!(". Source: {src}"))
+                    .unwrap_or("".to_owned())
+            );
+            Ok(())
+        }
+        Ok(()) => Ok(()),
+    }
+}
+
+/// Waits for ctrl-c or, on unix, for SIGTERM sent e.g. by systemd
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    let terminate = async {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                terminate.recv().await;
+            }
+            Err(err) => {
+                log::error!("Failed to listen for SIGTERM: {err}");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    let ctrl_c = async {
+        if let Err(err) = tokio::signal::ctrl_c().await {
+            log::error!("Failed to listen for the ctrl-c signal: {err}");
+            std::future::pending::<()>().await;
+        }
+    };
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+}
+
 pub struct EasyCarService {
-    pub bot: Arc<Bot>,
-    pub teloxide_token: String,
-    pub client: Arc<Mutex<InfoCarClient>>,
-    pub chat_id: ChatId,
+    /// Not set in the headless mode, when telegram isn't configured
+    pub bot: Option<Arc<Bot>>,
+    pub config: SharedConfig,
+    pub accounts: Arc<Vec<Arc<Account>>>,
+    supervisor: Supervisor,
 }
 
 #[derive(Error, Debug)]
 pub enum NewServiceError {
     #[error(transparent)]
     ClientError(#[from] NewClientError),
-    #[error("Failed to parse chat_id ({})", 0)]
-    ChatIdParseError(#[from] ParseIntError),
+    #[error(transparent)]
+    StateError(#[from] StateError),
+    #[error(transparent)]
+    NotifyError(#[from] NotifyError),
+}
+
+#[derive(Error, Debug)]
+pub enum StartError {
+    #[error(transparent)]
+    RequestError(#[from] RequestError),
+    #[error("Invalid telegram webhook URL: {0}")]
+    WebhookUrlError(String),
+    #[error("Failed to listen for the telegram webhook on {0}: {1}")]
+    WebhookBindError(SocketAddr, #[source] std::io::Error),
 }
 
 impl EasyCarService {
+    /// Creates the service. The configuration is reloaded when `config_file` changes. The bot is
+    /// only created if telegram is configured.
     pub async fn new(
-        teloxide_token: String,
-        user_data: UserData,
-        chat_id: String,
-        pesel: String,
-        phone_number: String,
-        driver_profile_id: ProfileIdType,
+        config: Config,
+        config_file: Option<PathBuf>,
     ) -> Result<Self, NewServiceError> {
+        // Installed before logging in, so that the metrics are recorded from the beginning
+        #[cfg(feature = "prometheus")]
+        if config.api.is_some() {
+            crate::api::metrics::install();
+        }
+        let bot = config
+            .notifiers
+            .telegram
+            .as_ref()
+            .map(|telegram| Arc::new(Bot::new(&telegram.token)));
+        let mut accounts = Vec::new();
+        for account in &config.accounts {
+            accounts.push(Arc::new(Account::new(&config, account, bot.clone()).await?));
+        }
+
+        let config = SharedConfig::new(config, config_file);
         Ok(Self {
-            bot: Arc::new(Bot::new(&teloxide_token)),
-            client: Arc::new(Mutex::new(
-                InfoCarClient::new(user_data, pesel, phone_number, driver_profile_id).await?,
-            )),
-            teloxide_token,
-            chat_id: ChatId(chat_id.parse()?),
+            supervisor: Supervisor::new(bot.clone(), config.clone()),
+            bot,
+            config,
+            accounts: Arc::new(accounts),
         })
     }
 
-    pub async fn start(self) -> Result<(), RequestError> {
+    /// Runs a supervised worker of the account
+    fn spawn_worker<F, Fut>(&self, name: &'static str, account: &Arc<Account>, worker: F)
+    where
+        F: Fn(Arc<Account>, SharedConfig, CancellationToken) -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let (worker_account, config) = (Arc::clone(account), self.config.clone());
+        self.supervisor
+            .spawn(name, Some(Arc::clone(account)), move |shutdown| {
+                worker(Arc::clone(&worker_account), config.clone(), shutdown)
+            });
+    }
+
+    fn spawn_workers(&self, account: &Arc<Account>) {
+        self.spawn_worker("session", account, |account, _, shutdown| {
+            session_worker(account, shutdown)
+        });
+        self.spawn_worker("scheduler", account, scheduler);
+        self.spawn_worker("reservation watcher", account, reservation_watcher);
+        self.spawn_worker("payment reminder", account, payment_reminder);
+        self.spawn_worker("exam reminder", account, exam_reminder);
+    }
+
+    #[cfg(feature = "api")]
+    fn spawn_api(&self) {
+        let Some(api) = self.config.current().api.clone() else {
+            return;
+        };
+        let accounts = Arc::clone(&self.accounts);
+        let config = self.config.clone();
+        self.supervisor.spawn("HTTP API", None, move |shutdown| {
+            let (api, accounts, config) = (api.clone(), Arc::clone(&accounts), config.clone());
+            async move {
+                if let Err(err) = crate::api::serve(api, accounts, config, shutdown).await {
+                    log::error!("The HTTP API failed: {err}");
+                }
+            }
+        });
+    }
+
+    #[cfg(not(feature = "api"))]
+    fn spawn_api(&self) {
+        if self.config.current().api.is_some() {
+            log::warn!("The HTTP API is configured, but easycar was built without the api feature");
+        }
+    }
+
+    pub async fn start(self) -> Result<(), StartError> {
+        self.run(None).await
+    }
+
+    /// Runs the service. The telegram webhook is served on `webhook_bind` instead of the
+    /// configured address if it's given.
+    async fn run(self, webhook_bind: Option<SocketAddr>) -> Result<(), StartError> {
         // Get a start date for the /uptime command
         let start_date = Utc::now();
 
-        tokio::spawn(session_worker(Arc::clone(&self.client)));
-        tokio::spawn(scheduler(
-            Arc::clone(&self.client),
-            Arc::clone(&self.bot),
-            self.chat_id,
-        ));
+        for account in self.accounts.iter() {
+            self.spawn_workers(account);
+        }
+        let (config, accounts) = (self.config.clone(), Arc::clone(&self.accounts));
+        self.supervisor
+            .spawn("config watcher", None, move |shutdown| {
+                config_watcher(config.clone(), Arc::clone(&accounts), shutdown)
+            });
+        #[cfg(unix)]
+        {
+            let (config, accounts) = (self.config.clone(), Arc::clone(&self.accounts));
+            self.supervisor
+                .spawn("systemd watchdog", None, move |shutdown| {
+                    systemd_watchdog(Arc::clone(&accounts), config.clone(), shutdown)
+                });
+        }
+        self.spawn_api();
+
+        let Some(bot) = self.bot.clone() else {
+            log::info!("Telegram is not configured, running in the headless mode");
+            shutdown_signal().await;
+            self.shutdown().await;
+            return Ok(());
+        };
 
-        self.bot.set_my_commands(Command::bot_commands()).await?;
-        self.bot
-            .set_chat_menu_button()
+        bot.set_my_commands(bot_commands(Language::English)).await?;
+        bot.set_my_commands(bot_commands(Language::Polish))
+            .language_code("pl")
+            .await?;
+        bot.set_chat_menu_button()
             .menu_button(MenuButton::Commands)
             .await?;
 
-        Command::repl(
-            self.bot,
-            move |bot: Arc<Bot>, msg: Message, cmd: Command| {
-                let client = Arc::clone(&self.client);
-                async move {
-                    if let Err(err) = answer(bot, msg, cmd, client, start_date).await {
-                        match err {
-                            AnswerError::TeloxideError(err) => return Err(err),
-                            _ => {
-                                log::error!(
-                                    "{err}{}",
-                                    err.source()
-                                        .map(|src| format!(". Source: {src}"))
-                                        .unwrap_or("".to_owned())
-                                );
-                            }
-                        }
-                    }
-                    Ok(())
-                }
-            },
-        )
-        .await;
+        let handler = dptree::entry()
+            .branch(
+                Update::filter_message()
+                    .branch(dptree::entry().filter_command::<Command>().endpoint(
+                        move |bot: Arc<Bot>,
+                              msg: Message,
+                              cmd: Command,
+                              accounts: Arc<Vec<Arc<Account>>>,
+                              config: SharedConfig| async move {
+                            handle_answer_error(
+                                answer(bot, msg, cmd, accounts, config, start_date).await,
+                            )
+                        },
+                    ))
+                    .endpoint(
+                        |bot: Arc<Bot>,
+                         msg: Message,
+                         accounts: Arc<Vec<Arc<Account>>>,
+                         config: SharedConfig| async move {
+                            handle_answer_error(answer_pay_prompt(bot, msg, accounts, config).await)
+                        },
+                    ),
+            )
+            .branch(Update::filter_callback_query().endpoint(
+                |bot: Arc<Bot>,
+                 query: CallbackQuery,
+                 accounts: Arc<Vec<Arc<Account>>>,
+                 config: SharedConfig| async move {
+                    handle_answer_error(answer_callback(bot, query, accounts, config).await)
+                },
+            ));
 
+        let mut dispatcher = Dispatcher::builder(Arc::clone(&bot), handler)
+            .dependencies(dptree::deps![
+                Arc::clone(&self.accounts),
+                self.config.clone()
+            ])
+            .build();
+        let dispatcher_shutdown = dispatcher.shutdown_token();
+        tokio::spawn(async move {
+            shutdown_signal().await;
+            match dispatcher_shutdown.shutdown() {
+                Ok(stopped) => stopped.await,
+                Err(err) => log::warn!("Failed to stop the bot: {err}"),
+            }
+        });
+        let webhook = self
+            .config
+            .current()
+            .notifiers
+            .telegram
+            .as_ref()
+            .and_then(|telegram| telegram.webhook.clone());
+        match webhook {
+            #[cfg(feature = "webhook")]
+            Some(webhook) => {
+                let listener = webhook::listener(bot, webhook, webhook_bind).await?;
+                dispatcher
+                    .dispatch_with_listener(
+                        listener,
+                        LoggingErrorHandler::with_custom_text("The telegram webhook failed"),
+                    )
+                    .await;
+            }
+            #[cfg(not(feature = "webhook"))]
+            Some(_) => {
+                log::warn!("The telegram webhook is configured, but easycar was built without the webhook feature. Using long polling");
+                let _ = webhook_bind;
+                dispatcher.dispatch().await;
+            }
+            None => dispatcher.dispatch().await,
+        }
+
+        self.shutdown().await;
         Ok(())
     }
+
+    /// Stops the workers, saves the state, optionally logs out and tells the users that the bot
+    /// is going offline. Gives up after the `shutdown.timeout`.
+    async fn shutdown(&self) {
+        let config = self.config.current();
+        let deadline = Instant::now() + config.shutdown.timeout.to_std();
+        log::info!("Shutting down...");
+
+        if timeout_at(deadline, self.supervisor.shutdown())
+            .await
+            .is_err()
+        {
+            log::warn!("Some workers didn't stop in time");
+        }
+        for account in self.accounts.iter() {
+            if timeout_at(deadline, self.stop_account(account, config.shutdown.logout))
+                .await
+                .is_err()
+            {
+                log::warn!("[{}] Failed to stop the account in time", account.name);
+            }
+        }
+        log::info!("Stopped");
+    }
+
+    async fn stop_account(&self, account: &Account, logout: bool) {
+        if let Err(err) = account.state.lock().await.save().await {
+            log::error!("[{}] Failed to save the state: {err}", account.name);
+        }
+        if logout {
+            match account.client.lock().await.logout().await {
+                Ok(()) => log::info!("[{}] Logged out", account.name),
+                Err(err) => log::error!("[{}] Failed to log out: {err}", account.name),
+            }
+        }
+        let _ = account
+            .notify(Notification::new(
+                Event::Shutdown,
+                &account.name,
+                account.language().text(Text::GoingOffline),
+            ))
+            .await;
+    }
 }
 
 #[cfg(feature = "shuttle")]
 #[shuttle_runtime::async_trait]
 impl shuttle_runtime::Service for EasyCarService {
-    async fn bind(mut self, _addr: std::net::SocketAddr) -> Result<(), shuttle_runtime::Error> {
-        self.start()
+    async fn bind(mut self, addr: SocketAddr) -> Result<(), shuttle_runtime::Error> {
+        self.run(Some(addr))
             .await
-            .expect("An error occurred while running the service!");
-        Ok(())
+            .map_err(|err| shuttle_runtime::Error::Custom(err.into()))
     }
 }
@@ -0,0 +1,396 @@
+use std::{
+    fmt,
+    fmt::Write,
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use info_car_api::types::{PossibleStatuses, ReservationListItem};
+use teloxide::{types::InlineKeyboardMarkup, utils::html};
+
+use crate::{
+    client::{FoundExam, GetExamsError, InfoCarClient, Step},
+    i18n::{Language, Text},
+    service::{callbacks::CallbackAction, workers::readable_exam_date, AnswerError, Reply},
+    utils::days_until,
+};
+
+/// Items shown on a single page, which keeps the messages well below the telegram limit of 4096
+/// characters
+const PAGE_SIZE: usize = 10;
+
+/// How long the page buttons reuse the fetched list
+const CACHE_LIFETIME: Duration = Duration::from_secs(5 * 60);
+
+/// The lists behind the paged messages of an account. Every page shows a slice of the whole list,
+/// which takes a request per watched WORD and category to fetch for the exams, so the page buttons
+/// reuse the list fetched by the command for a few minutes instead of downloading it again.
+#[derive(Default)]
+pub struct PageCache {
+    reservations: Mutex<Option<Cached<ReservationListItem>>>,
+    exams: Mutex<Option<Cached<FoundExam>>>,
+}
+
+struct Cached<T> {
+    fetched: Instant,
+    items: Arc<Vec<T>>,
+}
+
+/// Returns the cached list if it's recent enough, and otherwise fetches and caches a new one
+async fn cached<T, E>(
+    slot: &Mutex<Option<Cached<T>>>,
+    refresh: bool,
+    fetch: impl std::future::Future<Output = Result<Vec<T>, E>>,
+) -> Result<Arc<Vec<T>>, E> {
+    if !refresh {
+        let cached = slot.lock().expect("Page cache lock poisoned");
+        if let Some(cached) = cached
+            .as_ref()
+            .filter(|cached| cached.fetched.elapsed() < CACHE_LIFETIME)
+        {
+            return Ok(Arc::clone(&cached.items));
+        }
+    }
+    let items = Arc::new(fetch.await?);
+    *slot.lock().expect("Page cache lock poisoned") = Some(Cached {
+        fetched: Instant::now(),
+        items: Arc::clone(&items),
+    });
+    Ok(items)
+}
+
+/// Which reservations `/reservations` shows
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum ReservationFilter {
+    #[default]
+    All,
+    /// Holding a place on an exam
+    Active,
+    Cancelled,
+}
+
+impl ReservationFilter {
+    fn matches(self, item: &ReservationListItem) -> bool {
+        match self {
+            ReservationFilter::All => true,
+            ReservationFilter::Active => item.status.status.is_active(),
+            ReservationFilter::Cancelled => matches!(
+                item.status.status,
+                PossibleStatuses::Cancelled | PossibleStatuses::CancellationRequest
+            ),
+        }
+    }
+
+    /// Parses the filter, also given by its name in the `language`
+    pub fn parse(s: &str, language: Language) -> Result<Self, AnswerError> {
+        let filter = match s.trim() {
+            "" | "all" => ReservationFilter::All,
+            "active" => ReservationFilter::Active,
+            "cancelled" | "canceled" => ReservationFilter::Cancelled,
+            other => [
+                ReservationFilter::All,
+                ReservationFilter::Active,
+                ReservationFilter::Cancelled,
+            ]
+            .into_iter()
+            .find(|filter| filter.name(language) == other)
+            .ok_or_else(|| {
+                AnswerError::InvalidArgument(
+                    language.format(Text::UnknownReservationFilter, &[("filter", &other)]),
+                )
+            })?,
+        };
+        Ok(filter)
+    }
+
+    fn name(self, language: Language) -> &'static str {
+        language.text(match self {
+            ReservationFilter::All => Text::AllReservations,
+            ReservationFilter::Active => Text::ActiveReservations,
+            ReservationFilter::Cancelled => Text::CancelledReservations,
+        })
+    }
+}
+
+impl FromStr for ReservationFilter {
+    type Err = AnswerError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s, Language::default())
+    }
+}
+
+impl fmt::Display for ReservationFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ReservationFilter::All => "all",
+            ReservationFilter::Active => "active",
+            ReservationFilter::Cancelled => "cancelled",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// Returns the items on the `page`, the page actually shown and the number of pages. Pages past
+/// the end, e.g. after the list got shorter, show the last one.
+fn paginate<T>(items: &[T], page: usize) -> (&[T], usize, usize) {
+    let pages = items.len().div_ceil(PAGE_SIZE).max(1);
+    let page = page.min(pages - 1);
+    let start = page * PAGE_SIZE;
+    (
+        &items[start..(start + PAGE_SIZE).min(items.len())],
+        page,
+        pages,
+    )
+}
+
+/// The previous and next page buttons, if there is more than one page
+fn page_keyboard(
+    page: usize,
+    pages: usize,
+    language: Language,
+    action: impl Fn(usize) -> CallbackAction,
+) -> Option<InlineKeyboardMarkup> {
+    let mut buttons = Vec::new();
+    if page > 0 {
+        buttons.push(action(page - 1).button(language.text(Text::PreviousPage)));
+    }
+    if page + 1 < pages {
+        buttons.push(action(page + 1).button(language.text(Text::NextPage)));
+    }
+    (!buttons.is_empty()).then(|| InlineKeyboardMarkup::new([buttons]))
+}
+
+fn paged_reply(text: String, keyboard: Option<InlineKeyboardMarkup>) -> Reply {
+    match keyboard {
+        Some(keyboard) => Reply::with_keyboard(text, keyboard),
+        None => text.into(),
+    }
+}
+
+/// Renders a page of the reservations matching the `filter`. The list is fetched again only if
+/// `refresh` is set or the cached one is too old.
+pub async fn reservations(
+    client: &mut InfoCarClient,
+    cache: &PageCache,
+    refresh: bool,
+    filter: ReservationFilter,
+    page: usize,
+    language: Language,
+) -> Result<Reply, AnswerError> {
+    let reservations = cached(&cache.reservations, refresh, async {
+        client
+            .get_reservations()
+            .await
+            .map(|reservations| reservations.items)
+    })
+    .await?;
+    let items = reservations
+        .iter()
+        .filter(|item| filter.matches(item))
+        .collect::<Vec<_>>();
+    if items.is_empty() {
+        return Ok(match filter {
+            ReservationFilter::All => language.text(Text::NoReservations).to_owned(),
+            filter => language.format(
+                Text::NoFilteredReservations,
+                &[("filter", &filter.name(language))],
+            ),
+        }
+        .into());
+    }
+
+    let (items, page, pages) = paginate(&items, page);
+    let mut text = language.format(
+        Text::ReservationsHeader,
+        &[
+            ("filter", &filter.name(language)),
+            ("page", &(page + 1)),
+            ("pages", &pages),
+        ],
+    );
+    for item in items {
+        let _ = write!(
+            text,
+            "\n\n{}",
+            language.format(
+                Text::ReservationItem,
+                &[
+                    ("date", &readable_exam_date(&item.exam, language)),
+                    ("word", &html::escape(&item.exam.organization_unit_name)),
+                    ("status", &language.status(&item.status.status)),
+                    ("id", &item.id),
+                ],
+            )
+        );
+    }
+    let keyboard = page_keyboard(page, pages, language, |page| {
+        CallbackAction::ReservationsPage(filter, page)
+    });
+    Ok(paged_reply(text, keyboard))
+}
+
+/// Renders a page of the exams meeting the watch criteria, describing every request with
+/// `on_step`. The exams are searched again only if `refresh` is set or the cached ones are too old.
+pub async fn exams(
+    client: &mut InfoCarClient,
+    cache: &PageCache,
+    refresh: bool,
+    page: usize,
+    language: Language,
+    on_step: &(dyn Fn(Step) + Send + Sync),
+) -> Result<Reply, AnswerError> {
+    let exams = cached(&cache.exams, refresh, async {
+        match client
+            .get_nearest_exams_with_steps(usize::MAX, on_step)
+            .await
+        {
+            Err(GetExamsError::NoExamsError) => Ok(Vec::new()),
+            result => result,
+        }
+    })
+    .await?;
+    if exams.is_empty() {
+        return Ok(language.text(Text::NoExams).to_owned().into());
+    }
+
+    let (exams, page, pages) = paginate(&exams, page);
+    let mut text = language.format(
+        Text::ExamsHeader,
+        &[("page", &(page + 1)), ("pages", &pages)],
+    );
+    for found in exams {
+        let _ = write!(text, "\n{}", format_exam(found, language));
+    }
+    let keyboard = page_keyboard(page, pages, language, CallbackAction::ExamsPage);
+    Ok(paged_reply(text, keyboard))
+}
+
+fn format_exam(found: &FoundExam, language: Language) -> String {
+    language.format(
+        Text::ExamItem,
+        &[
+            ("id", &found.exam.id),
+            ("date", &language.date_from_string(&found.exam.date)),
+            ("days", &days_until(&found.exam.date)),
+            ("kind", &language.exam_kind(found.kind)),
+            ("category", &found.category),
+            ("word", &found.word_id),
+        ],
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use teloxide::types::InlineKeyboardButtonKind;
+
+    use super::*;
+
+    fn callbacks(keyboard: Option<InlineKeyboardMarkup>) -> Vec<String> {
+        keyboard
+            .into_iter()
+            .flat_map(|keyboard| keyboard.inline_keyboard)
+            .flatten()
+            .filter_map(|button| match button.kind {
+                InlineKeyboardButtonKind::CallbackData(data) => Some(data),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn paginates_empty_list() {
+        let items: [u8; 0] = [];
+        assert_eq!(paginate(&items, 0), (&items[..], 0, 1));
+        assert_eq!(paginate(&items, 3), (&items[..], 0, 1));
+    }
+
+    #[test]
+    fn paginates_partial_last_page() {
+        let items = (0..25).collect::<Vec<_>>();
+        assert_eq!(paginate(&items, 0), (&items[..10], 0, 3));
+        assert_eq!(paginate(&items, 1), (&items[10..20], 1, 3));
+        assert_eq!(paginate(&items, 2), (&items[20..], 2, 3));
+    }
+
+    #[test]
+    fn paginates_exact_multiple() {
+        let items = (0..20).collect::<Vec<_>>();
+        assert_eq!(paginate(&items, 1), (&items[10..], 1, 2));
+        assert_eq!(paginate(&items[..10], 0), (&items[..10], 0, 1));
+    }
+
+    #[test]
+    fn clamps_pages_past_the_end() {
+        let items = (0..15).collect::<Vec<_>>();
+        assert_eq!(paginate(&items, 2), (&items[10..], 1, 2));
+        assert_eq!(paginate(&items, usize::MAX), (&items[10..], 1, 2));
+    }
+
+    #[test]
+    fn page_buttons() {
+        let language = Language::default();
+        assert!(page_keyboard(0, 1, language, CallbackAction::ExamsPage).is_none());
+        assert_eq!(
+            callbacks(page_keyboard(0, 3, language, CallbackAction::ExamsPage)),
+            ["exams:1"]
+        );
+        assert_eq!(
+            callbacks(page_keyboard(1, 3, language, CallbackAction::ExamsPage)),
+            ["exams:0", "exams:2"]
+        );
+        assert_eq!(
+            callbacks(page_keyboard(2, 3, language, |page| {
+                CallbackAction::ReservationsPage(ReservationFilter::Active, page)
+            })),
+            ["reservations:active:1"]
+        );
+    }
+
+    #[test]
+    fn parses_reservation_filters() {
+        for filter in [
+            ReservationFilter::All,
+            ReservationFilter::Active,
+            ReservationFilter::Cancelled,
+        ] {
+            assert_eq!(
+                filter.to_string().parse::<ReservationFilter>().ok(),
+                Some(filter)
+            );
+            for language in Language::ALL {
+                assert_eq!(
+                    ReservationFilter::parse(filter.name(language), language).ok(),
+                    Some(filter)
+                );
+            }
+        }
+        assert_eq!(
+            "".parse::<ReservationFilter>().ok(),
+            Some(ReservationFilter::All)
+        );
+        assert!("paid".parse::<ReservationFilter>().is_err());
+    }
+
+    #[tokio::test]
+    async fn reuses_recent_lists() {
+        let slot = Mutex::new(None);
+        let fetch = |items: Vec<u8>| async { Ok::<_, ()>(items) };
+
+        let first = cached(&slot, false, fetch(vec![1])).await.unwrap();
+        assert_eq!(*first, [1]);
+        let reused = cached(&slot, false, fetch(vec![2])).await.unwrap();
+        assert_eq!(*reused, [1]);
+        let refreshed = cached(&slot, true, fetch(vec![3])).await.unwrap();
+        assert_eq!(*refreshed, [3]);
+
+        // The monotonic clock may start at the boot
+        let Some(expired) = Instant::now().checked_sub(CACHE_LIFETIME) else {
+            return;
+        };
+        slot.lock().unwrap().as_mut().unwrap().fetched = expired;
+        let expired = cached(&slot, false, fetch(vec![4])).await.unwrap();
+        assert_eq!(*expired, [4]);
+    }
+}
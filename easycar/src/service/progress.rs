@@ -0,0 +1,132 @@
+use std::{future::Future, sync::Arc};
+
+use teloxide::{
+    prelude::*,
+    types::{ChatAction, MessageId},
+    RequestError,
+};
+use tokio::{
+    sync::{watch, Mutex, MutexGuard},
+    task::JoinHandle,
+    time::{interval, sleep, sleep_until, Duration, Instant},
+};
+
+use crate::i18n::{Language, Text};
+
+/// Telegram shows a chat action for 5 seconds
+const CHAT_ACTION_INTERVAL: Duration = Duration::from_secs(4);
+/// The status message is edited at most this often, to stay within the rate limits
+const EDIT_INTERVAL: Duration = Duration::from_secs(3);
+
+/// Shows the user that a slow command is being handled: the "typing" chat action and a status
+/// message with the current step
+pub struct Progress {
+    step: watch::Sender<String>,
+    task: JoinHandle<Option<MessageId>>,
+    language: Language,
+}
+
+impl Progress {
+    pub fn start(bot: Arc<Bot>, chat_id: ChatId, language: Language) -> Self {
+        let (step, steps) = watch::channel(language.text(Text::WorkingOnIt).to_owned());
+        Self {
+            step,
+            task: tokio::spawn(show_progress(bot, chat_id, steps)),
+            language,
+        }
+    }
+
+    pub fn language(&self) -> Language {
+        self.language
+    }
+
+    /// Describes the current step, shown on the next edit of the status message
+    pub fn step(&self, step: impl Into<String>) {
+        self.step.send_replace(step.into());
+    }
+
+    /// Locks the `mutex`, telling the user when it's held by a background worker
+    pub async fn lock<'a, T>(&self, mutex: &'a Mutex<T>) -> MutexGuard<'a, T> {
+        match mutex.try_lock() {
+            Ok(guard) => guard,
+            Err(_) => {
+                self.step(self.language.text(Text::WaitingForWorkers));
+                mutex.lock().await
+            }
+        }
+    }
+
+    /// Stops showing the progress. Returns the status message, which can be replaced with the
+    /// result, unless it couldn't be sent.
+    pub async fn finish(self) -> Option<MessageId> {
+        drop(self.step);
+        self.task.await.ok().flatten()
+    }
+}
+
+async fn show_progress(
+    bot: Arc<Bot>,
+    chat_id: ChatId,
+    mut steps: watch::Receiver<String>,
+) -> Option<MessageId> {
+    let mut message = None;
+    let mut shown = None;
+    let mut next_edit = Instant::now();
+    let mut chat_action = interval(CHAT_ACTION_INTERVAL);
+
+    loop {
+        let outdated = shown.as_ref() != Some(&*steps.borrow());
+        tokio::select! {
+            changed = steps.changed() => {
+                if changed.is_err() {
+                    return message;
+                }
+            }
+            _ = chat_action.tick() => {
+                if let Err(err) = bot.send_chat_action(chat_id, ChatAction::Typing).await {
+                    log::warn!("Failed to send the chat action: {err}");
+                    if let RequestError::RetryAfter(after) = err {
+                        next_edit = next_edit.max(Instant::now() + after.duration());
+                    }
+                }
+            }
+            _ = sleep_until(next_edit), if outdated => {
+                let step = steps.borrow_and_update().clone();
+                let text = format!("⏳ {step}…");
+                let result = match message {
+                    Some(id) => bot.edit_message_text(chat_id, id, text).await.map(|_| ()),
+                    None => bot
+                        .send_message(chat_id, text)
+                        .await
+                        .map(|sent| message = Some(sent.id)),
+                };
+                next_edit = Instant::now() + EDIT_INTERVAL;
+                match result {
+                    Ok(()) => shown = Some(step),
+                    Err(RequestError::RetryAfter(after)) => {
+                        next_edit = Instant::now() + after.duration();
+                    }
+                    Err(err) => {
+                        log::warn!("Failed to show the progress: {err}");
+                        shown = Some(step);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Sends the request again if telegram asks to slow down
+pub async fn retry_after<T, Fut>(request: impl Fn() -> Fut) -> Result<T, RequestError>
+where
+    Fut: Future<Output = Result<T, RequestError>>,
+{
+    match request().await {
+        Err(RequestError::RetryAfter(after)) => {
+            log::warn!("Hit the telegram rate limit, retrying in {after:?}");
+            sleep(after.duration()).await;
+            request().await
+        }
+        result => result,
+    }
+}
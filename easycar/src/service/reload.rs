@@ -0,0 +1,188 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use notify::{RecursiveMode, Watcher};
+use teloxide::utils::html;
+use thiserror::Error;
+use tokio::{
+    sync::{mpsc, watch, Mutex},
+    time::{sleep, Duration},
+};
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    account::Account,
+    config::{Config, ConfigError},
+    i18n::Text,
+    notifier::{Event, Notification, NotifyError},
+};
+
+#[derive(Error, Debug)]
+pub enum ReloadError {
+    #[error("The configuration wasn't loaded from a file, so it can't be reloaded")]
+    NoConfigFile,
+    #[error(transparent)]
+    ConfigError(#[from] ConfigError),
+    #[error("{0} can't be changed without a restart")]
+    RequiresRestart(&'static str),
+    #[error(transparent)]
+    NotifyError(#[from] NotifyError),
+}
+
+/// The configuration shared by the workers and the commands. It is atomically replaced on reload.
+#[derive(Clone)]
+pub struct SharedConfig {
+    sender: Arc<watch::Sender<Arc<Config>>>,
+    path: Option<PathBuf>,
+    /// Held during a reload, so the concurrent ones are applied in the same order everywhere
+    reload_lock: Arc<Mutex<()>>,
+}
+
+impl SharedConfig {
+    pub fn new(config: Config, path: Option<PathBuf>) -> Self {
+        Self {
+            sender: Arc::new(watch::Sender::new(Arc::new(config))),
+            path,
+            reload_lock: Arc::new(Mutex::new(())),
+        }
+    }
+
+    pub fn current(&self) -> Arc<Config> {
+        Arc::clone(&self.sender.borrow())
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Loads the configuration file and applies it to the running accounts. The old configuration
+    /// stays active if the new one is invalid.
+    pub async fn reload(&self, accounts: &[Arc<Account>]) -> Result<(), ReloadError> {
+        let path = self.path.as_ref().ok_or(ReloadError::NoConfigFile)?;
+        let _guard = self.reload_lock.lock().await;
+        let new = Config::load(path)?;
+        let old = self.current();
+        check_compatible(&old, &new)?;
+
+        // Build every notifier first, so a failure doesn't leave the accounts half updated
+        let notifiers = accounts
+            .iter()
+            .map(|account| account.build_notifier(&new))
+            .collect::<Result<Vec<_>, _>>()?;
+        for (account, notifier) in accounts.iter().zip(notifiers) {
+            account.apply_config(&new).await;
+            account.set_notifier(notifier);
+        }
+        self.sender.send_replace(Arc::new(new));
+
+        log::info!("Reloaded the configuration from {}", path.display());
+        Ok(())
+    }
+}
+
+/// Checks whether the new configuration only changes the settings which can be applied at runtime
+fn check_compatible(old: &Config, new: &Config) -> Result<(), ReloadError> {
+    let identity = |config: &Config| {
+        config
+            .accounts
+            .iter()
+            .map(|account| {
+                (
+                    account.name.clone(),
+                    account.username.clone(),
+                    account.password.clone(),
+                    account.pesel.clone(),
+                    account.phone_number.clone(),
+                    account.driver_profile.clone(),
+                    account.chat_id,
+                )
+            })
+            .collect::<Vec<_>>()
+    };
+
+    if identity(old) != identity(new) {
+        return Err(ReloadError::RequiresRestart("The list of accounts"));
+    }
+    if old.notifiers.telegram != new.notifiers.telegram {
+        return Err(ReloadError::RequiresRestart("The telegram settings"));
+    }
+    if old.api != new.api {
+        return Err(ReloadError::RequiresRestart("The HTTP API"));
+    }
+    if old.storage != new.storage {
+        return Err(ReloadError::RequiresRestart("The storage"));
+    }
+    Ok(())
+}
+
+/// Reloads the configuration whenever its file changes and reports the result to the accounts
+pub async fn config_watcher(
+    config: SharedConfig,
+    accounts: Arc<Vec<Arc<Account>>>,
+    shutdown: CancellationToken,
+) {
+    let Some(path) = config.path().map(Path::to_path_buf) else {
+        return;
+    };
+    let (tx, mut rx) = mpsc::unbounded_channel();
+
+    // Editors often replace the file instead of writing to it, so the directory is watched
+    let file_name = path.file_name().map(|name| name.to_owned());
+    let mut watcher =
+        match notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+            if let Ok(event) = event {
+                if (event.kind.is_modify() || event.kind.is_create())
+                    && event
+                        .paths
+                        .iter()
+                        .any(|path| path.file_name() == file_name.as_deref())
+                {
+                    let _ = tx.send(());
+                }
+            }
+        }) {
+            Ok(watcher) => watcher,
+            Err(err) => {
+                log::error!("Failed to create the configuration watcher: {err}");
+                return;
+            }
+        };
+    let directory = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    if let Err(err) = watcher.watch(&directory, RecursiveMode::NonRecursive) {
+        log::error!("Failed to watch {}: {err}", directory.display());
+        return;
+    }
+    log::info!("Watching {} for changes", path.display());
+
+    while let Some(Some(())) = shutdown.run_until_cancelled(rx.recv()).await {
+        // Wait for the writes to settle before reading the file
+        sleep(Duration::from_secs(1)).await;
+        while rx.try_recv().is_ok() {}
+
+        let result = config.reload(&accounts).await;
+        if let Err(err) = &result {
+            log::error!("Failed to reload the configuration: {err}");
+        }
+        for account in accounts.iter() {
+            let language = account.language();
+            let (event, message) = match &result {
+                Ok(()) => (Event::Reload, language.text(Text::Reloaded).to_owned()),
+                Err(err) => (
+                    Event::Error,
+                    language.format(
+                        Text::ReloadFailedNotification,
+                        &[("error", &html::escape(&err.to_string()))],
+                    ),
+                ),
+            };
+            let _ = account
+                .notify(Notification::new(event, &account.name, message))
+                .await;
+        }
+    }
+}
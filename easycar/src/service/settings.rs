@@ -0,0 +1,306 @@
+use std::{num::NonZeroU32, str::FromStr};
+
+use chrono::{Duration, NaiveDate, NaiveTime, Weekday};
+use info_car_api::types::LicenseCategory;
+
+use super::AnswerError;
+use crate::{
+    account::Account,
+    config::{Config, ExamFilter, HumanDuration, WatchConfig},
+    i18n::{Language, Text},
+    utils::parse_duration,
+};
+
+fn join<T: ToString>(values: &[T], separator: &str) -> String {
+    values
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(separator)
+}
+
+/// Describes the configuration in effect for the account
+pub async fn show_settings(account: &Account, config: &Config) -> String {
+    let criteria = account.client.lock().await.criteria().clone();
+    let state = account.state.lock().await;
+    let state = state.state();
+    let exam_reminders = state
+        .exam_reminder_offsets
+        .clone()
+        .unwrap_or_else(|| config.reminders.exam_offsets());
+    let intervals = &config.poll_intervals;
+    let language = account.language();
+
+    language.format(
+        Text::Settings,
+        &[
+            (
+                "overridden",
+                &if state.watch_override.is_some() {
+                    language.text(Text::WatchOverridden)
+                } else {
+                    ""
+                },
+            ),
+            ("words", &join(&criteria.words, ", ")),
+            ("categories", &join(&criteria.categories, ", ")),
+            (
+                "exam_kinds",
+                &join(
+                    &criteria
+                        .exam_kinds
+                        .iter()
+                        .map(|kind| language.exam_kind(*kind))
+                        .collect::<Vec<_>>(),
+                    ", ",
+                ),
+            ),
+            ("horizon", &criteria.horizon),
+            ("filter", &criteria.filter),
+            ("exams", &intervals.exams),
+            ("reservations", &intervals.reservations),
+            ("payments", &intervals.payments),
+            ("reminders", &intervals.reminders),
+            ("payment", &join(&config.reminders.payment, ", ")),
+            ("payment_window", &config.reminders.payment_window),
+            (
+                "exam",
+                &join(
+                    &exam_reminders
+                        .into_iter()
+                        .filter_map(|offset| Duration::try_minutes(offset).map(HumanDuration))
+                        .collect::<Vec<_>>(),
+                    ", ",
+                ),
+            ),
+            ("language", &language.name()),
+        ],
+    )
+}
+
+/// Changes the watch criteria of the account and saves them in its state
+pub async fn update_criteria(
+    account: &Account,
+    f: impl FnOnce(&mut WatchConfig) -> Result<(), AnswerError>,
+) -> Result<WatchConfig, AnswerError> {
+    let mut client = account.client.lock().await;
+    let mut criteria = client.criteria().clone();
+    f(&mut criteria)?;
+    criteria
+        .validate("watch")
+        .map_err(|err| AnswerError::InvalidArgument(err.to_string()))?;
+
+    account
+        .state
+        .lock()
+        .await
+        .update(|state| state.watch_override = Some(criteria.clone()))
+        .await?;
+    client.set_criteria(criteria.clone());
+    log::info!("[{}] Watch criteria changed to {criteria:?}", account.name);
+
+    Ok(criteria)
+}
+
+/// Forgets the criteria changed from Telegram and goes back to the configuration
+pub async fn reset_criteria(account: &Account, config: &Config) -> Result<(), AnswerError> {
+    account
+        .state
+        .lock()
+        .await
+        .update(|state| state.watch_override = None)
+        .await?;
+    account.apply_config(config).await;
+    Ok(())
+}
+
+fn parse_word(word: Option<&str>, language: Language) -> Result<NonZeroU32, AnswerError> {
+    let word = word.ok_or(AnswerError::TooFewArguments(2, 1))?;
+    word.parse().map_err(|_| {
+        AnswerError::InvalidArgument(language.format(Text::NotAWordId, &[("word", &word)]))
+    })
+}
+
+/// Handles `/watch [add|remove <word>|reset]`
+pub async fn watch(args: &str, account: &Account, config: &Config) -> Result<String, AnswerError> {
+    let language = account.language();
+    let mut args = args.split_whitespace();
+    match args.next() {
+        None => {}
+        Some("add") => {
+            let word = parse_word(args.next(), language)?;
+            update_criteria(account, |criteria| {
+                if !criteria.words.contains(&word) {
+                    criteria.words.push(word);
+                }
+                Ok(())
+            })
+            .await?;
+        }
+        Some("remove") => {
+            let word = parse_word(args.next(), language)?;
+            update_criteria(account, |criteria| {
+                criteria.words.retain(|id| *id != word);
+                Ok(())
+            })
+            .await?;
+        }
+        Some("reset") => reset_criteria(account, config).await?,
+        Some(other) => {
+            return Err(AnswerError::InvalidArgument(
+                language.format(Text::UnknownWatchAction, &[("action", &other)]),
+            ))
+        }
+    }
+
+    let criteria = account.client.lock().await.criteria().clone();
+    Ok(language.format(
+        Text::WatchedWords,
+        &[("words", &join(&criteria.words, ", "))],
+    ))
+}
+
+/// Handles `/category [categories...]`
+pub async fn category(args: &str, account: &Account) -> Result<String, AnswerError> {
+    let categories = args
+        .split_whitespace()
+        .map(LicenseCategory::from_str)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(AnswerError::InvalidArgument)?;
+
+    let criteria = if categories.is_empty() {
+        account.client.lock().await.criteria().clone()
+    } else {
+        update_criteria(account, |criteria| {
+            criteria.categories = categories;
+            Ok(())
+        })
+        .await?
+    };
+    Ok(account.language().format(
+        Text::WatchedCategories,
+        &[("categories", &join(&criteria.categories, ", "))],
+    ))
+}
+
+/// Handles `/horizon [days]`. Durations such as `2w` are accepted too.
+pub async fn horizon(args: &str, account: &Account) -> Result<String, AnswerError> {
+    let language = account.language();
+    let args = args.trim();
+    let criteria = if args.is_empty() {
+        account.client.lock().await.criteria().clone()
+    } else {
+        let horizon = args
+            .parse::<i64>()
+            .ok()
+            .and_then(Duration::try_days)
+            .or_else(|| parse_duration(args))
+            .ok_or_else(|| {
+                AnswerError::InvalidArgument(
+                    language.format(Text::NotADayCount, &[("value", &args)]),
+                )
+            })?;
+        update_criteria(account, |criteria| {
+            criteria.horizon = HumanDuration(horizon);
+            Ok(())
+        })
+        .await?
+    };
+    Ok(language.format(Text::Horizon, &[("horizon", &criteria.horizon)]))
+}
+
+fn invalid_value(text: Text, key: &str, value: &str, language: Language) -> AnswerError {
+    AnswerError::InvalidArgument(language.format(text, &[("key", &key), ("value", &value)]))
+}
+
+fn parse_value<T: FromStr>(key: &str, value: &str, language: Language) -> Result<T, AnswerError> {
+    value
+        .parse()
+        .map_err(|_| invalid_value(Text::InvalidValue, key, value, language))
+}
+
+fn parse_date(key: &str, value: &str, language: Language) -> Result<NaiveDate, AnswerError> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map_err(|_| invalid_value(Text::InvalidDate, key, value, language))
+}
+
+fn parse_time(key: &str, value: &str, language: Language) -> Result<NaiveTime, AnswerError> {
+    NaiveTime::parse_from_str(value, "%H:%M")
+        .map_err(|_| invalid_value(Text::InvalidTime, key, value, language))
+}
+
+/// Changes a single field of the filter. An empty value clears it.
+fn set_filter(
+    filter: &mut ExamFilter,
+    key: &str,
+    values: &[&str],
+    language: Language,
+) -> Result<(), AnswerError> {
+    let value = values.first().copied();
+    let date = |v| parse_date(key, v, language);
+    let time = |v| parse_time(key, v, language);
+    match key {
+        "not_before" => filter.not_before = value.map(date).transpose()?,
+        "not_after" => filter.not_after = value.map(date).transpose()?,
+        "weekdays" => {
+            filter.weekdays = values
+                .iter()
+                .map(|v| parse_value::<Weekday>(key, v, language))
+                .collect::<Result<_, _>>()?
+        }
+        "earliest_time" => filter.earliest_time = value.map(time).transpose()?,
+        "latest_time" => filter.latest_time = value.map(time).transpose()?,
+        "min_places" => {
+            filter.min_places = value.map(|v| parse_value(key, v, language)).transpose()?
+        }
+        "max_price" => {
+            filter.max_price = value.map(|v| parse_value(key, v, language)).transpose()?
+        }
+        "clear" => *filter = ExamFilter::default(),
+        _ => {
+            return Err(AnswerError::InvalidArgument(
+                language.format(Text::UnknownExamFilter, &[("key", &key)]),
+            ))
+        }
+    }
+    Ok(())
+}
+
+/// Handles `/filter [field [values...]|clear]`
+pub async fn filter(args: &str, account: &Account) -> Result<String, AnswerError> {
+    let language = account.language();
+    let args: Vec<&str> = args.split_whitespace().collect();
+    let criteria = match args.split_first() {
+        None => account.client.lock().await.criteria().clone(),
+        Some((key, values)) => {
+            update_criteria(account, |criteria| {
+                set_filter(&mut criteria.filter, key, values, language)
+            })
+            .await?
+        }
+    };
+    Ok(language.format(Text::ExamFilter, &[("filter", &criteria.filter)]))
+}
+
+/// Handles `/language [en|pl|reset]`
+pub async fn language(
+    args: &str,
+    account: &Account,
+    config: &Config,
+) -> Result<String, AnswerError> {
+    let language = match args.trim() {
+        "" => account.language(),
+        "reset" => account.set_language(None, config).await?,
+        args => {
+            let language = args.parse().map_err(|_| {
+                AnswerError::InvalidArgument(
+                    account
+                        .language()
+                        .format(Text::UnknownLanguage, &[("language", &args)]),
+                )
+            })?;
+            account.set_language(Some(language), config).await?
+        }
+    };
+    Ok(language.format(Text::Language, &[("language", &language.name())]))
+}
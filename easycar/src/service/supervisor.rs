@@ -0,0 +1,160 @@
+use std::{any::Any, future::Future, sync::Arc};
+
+use teloxide::{prelude::*, types::ParseMode, utils::html};
+use tokio::time::{sleep, Duration, Instant};
+use tokio_util::{sync::CancellationToken, task::TaskTracker};
+
+use crate::{
+    account::Account,
+    i18n::{Language, Text},
+    notifier::{Event, Notification},
+    service::reload::SharedConfig,
+    utils::short_duration,
+};
+
+/// The first delay before restarting a crashed worker. It doubles with every crash.
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(5 * 60);
+/// A worker which ran for this long before crashing is restarted with the initial delay
+const STABLE_RUN: Duration = Duration::from_secs(10 * 60);
+
+/// Restarts the crashed background workers and reports the crashes. Stops them on shutdown.
+#[derive(Clone)]
+pub struct Supervisor {
+    bot: Option<Arc<Bot>>,
+    config: SharedConfig,
+    shutdown: CancellationToken,
+    tasks: TaskTracker,
+}
+
+fn panic_message(panic: Box<dyn Any + Send>) -> String {
+    panic
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| panic.downcast_ref::<String>().cloned())
+        .unwrap_or("unknown panic".to_owned())
+}
+
+impl Supervisor {
+    pub fn new(bot: Option<Arc<Bot>>, config: SharedConfig) -> Self {
+        Self {
+            bot,
+            config,
+            shutdown: CancellationToken::new(),
+            tasks: TaskTracker::new(),
+        }
+    }
+
+    /// Runs the worker created by `start` until it finishes, restarting it whenever it panics.
+    /// The crashes are reported to the `account` and to the admin chat. The worker is given a
+    /// token, which is cancelled on shutdown.
+    pub fn spawn<F, Fut>(&self, name: &'static str, account: Option<Arc<Account>>, start: F)
+    where
+        F: Fn(CancellationToken) -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let supervisor = self.clone();
+        self.tasks.spawn(async move {
+            let mut backoff = INITIAL_BACKOFF;
+            loop {
+                let started = Instant::now();
+                let worker = tokio::spawn(start(supervisor.shutdown.clone()));
+                let error = match worker.await {
+                    Ok(()) => {
+                        log::debug!("The {name} worker finished");
+                        return;
+                    }
+                    Err(err) if err.is_panic() => panic_message(err.into_panic()),
+                    Err(err) => err.to_string(),
+                };
+
+                if started.elapsed() >= STABLE_RUN {
+                    backoff = INITIAL_BACKOFF;
+                }
+                if supervisor.shutdown.is_cancelled() {
+                    log::error!("The {name} worker crashed while shutting down: {error}");
+                    return;
+                }
+                supervisor
+                    .report_crash(name, account.as_deref(), &error, backoff)
+                    .await;
+                if supervisor
+                    .shutdown
+                    .run_until_cancelled(sleep(backoff))
+                    .await
+                    .is_none()
+                {
+                    return;
+                }
+                backoff = (backoff * 2).min(MAX_BACKOFF);
+            }
+        });
+    }
+
+    /// Asks the workers to stop and waits until they finish
+    pub async fn shutdown(&self) {
+        self.shutdown.cancel();
+        self.tasks.close();
+        self.tasks.wait().await;
+    }
+
+    async fn report_crash(
+        &self,
+        name: &'static str,
+        account: Option<&Account>,
+        error: &str,
+        restart_in: Duration,
+    ) {
+        let delay = short_duration(
+            chrono::Duration::from_std(restart_in).unwrap_or(chrono::Duration::zero()),
+        );
+        let prefix = account
+            .map(|account| format!("[{}] ", account.name))
+            .unwrap_or_default();
+        log::error!("{prefix}The {name} worker crashed: {error}. Restarting it in {delay}");
+        let message = |language: Language| {
+            language.format(
+                Text::WorkerCrashed,
+                &[
+                    ("account", &html::escape(&prefix)),
+                    ("worker", &name),
+                    ("error", &html::escape(error)),
+                    ("delay", &delay),
+                ],
+            )
+        };
+
+        if let Some(account) = account {
+            account.health.worker_crashed(name, error.to_owned());
+            let _ = account
+                .notify(Notification::new(
+                    Event::Error,
+                    &account.name,
+                    message(account.language()),
+                ))
+                .await;
+        }
+
+        let config = self.config.current();
+        let admin = config
+            .notifiers
+            .telegram
+            .as_ref()
+            .and_then(|telegram| telegram.admin_chat_id)
+            .map(ChatId);
+        let (Some(bot), Some(admin)) = (&self.bot, admin) else {
+            return;
+        };
+        // The account's chat was already notified
+        if account.is_some_and(|account| account.chat_id == Some(admin)) {
+            return;
+        }
+        if let Err(err) = bot
+            .send_message(admin, message(Language::default()))
+            .parse_mode(ParseMode::Html)
+            .await
+        {
+            log::error!("Failed to report the crash to the admin chat: {err}");
+        }
+    }
+}
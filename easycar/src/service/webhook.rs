@@ -0,0 +1,50 @@
+use std::{convert::Infallible, net::SocketAddr, sync::Arc};
+
+use teloxide::{
+    prelude::*,
+    update_listeners::{
+        webhooks::{axum_to_router, Options},
+        UpdateListener,
+    },
+};
+use tokio::net::TcpListener;
+
+use crate::{config::TelegramWebhookConfig, service::StartError};
+
+/// Serves the webhook on `bind`, or on the address from the configuration, and sets it up in
+/// telegram. The webhook is deleted when the listener is stopped.
+pub async fn listener(
+    bot: Arc<Bot>,
+    config: TelegramWebhookConfig,
+    bind: Option<SocketAddr>,
+) -> Result<impl UpdateListener<Err = Infallible>, StartError> {
+    let address = bind.unwrap_or(config.bind);
+    let url = reqwest::Url::parse(&config.url)
+        .map_err(|err| StartError::WebhookUrlError(err.to_string()))?;
+    let mut options = Options::new(address, url);
+    if let Some(secret_token) = config.secret_token {
+        options = options.secret_token(secret_token);
+    }
+
+    // Bound before setting the webhook, so that it isn't left pointing at nothing
+    let tcp_listener = TcpListener::bind(address)
+        .await
+        .map_err(|err| StartError::WebhookBindError(address, err))?;
+    let (mut listener, stop_flag, router) = axum_to_router(bot, options).await?;
+    log::info!(
+        "Receiving the telegram updates at {} on {address}",
+        config.url
+    );
+
+    let stop_token = listener.stop_token();
+    tokio::spawn(async move {
+        if let Err(err) = axum::serve(tcp_listener, router)
+            .with_graceful_shutdown(stop_flag)
+            .await
+        {
+            log::error!("The telegram webhook failed: {err}");
+            stop_token.stop();
+        }
+    });
+    Ok(listener)
+}
@@ -1,46 +1,73 @@
 use crate::{
-    client::{GetExamsError, InfoCarClient},
-    utils::{date_from_string, readable_date_from_string},
+    account::Account,
+    client::{FoundExam, GetExamsError, InfoCarClient},
+    config::{PollIntervals, TemplatesConfig},
+    i18n::{Language, Text},
+    notifier::{Event, Notification, Venue},
+    service::{callbacks::pay_keyboard, format_current_exam, reload::SharedConfig},
+    state::PaymentReminder,
+    utils::{days_until, parse_api_timestamp, try_date_from_string},
 };
-use chrono::{Duration as ChronoDuration, Utc};
-use info_car_api::error::EnrollError;
-use std::{error::Error, sync::Arc};
-use teloxide::{prelude::*, types::ParseMode};
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use info_car_api::{
+    error::EnrollError,
+    types::{PossibleStatuses, ReservationExam, ReservationListItem},
+};
+use metrics::{counter, gauge};
+use std::{collections::HashSet, error::Error, fmt::Write, path::Path, sync::Arc};
+use teloxide::utils::html;
 use tokio::{
     sync::Mutex,
-    time::{interval, sleep, Duration as TokioDuration},
+    time::{interval, interval_at, sleep, Duration as TokioDuration, Instant, Interval},
 };
+use tokio_util::sync::CancellationToken;
+
+/// Restarts the interval when its period was changed by a configuration reload
+fn follow_config(
+    interval: &mut Interval,
+    config: &SharedConfig,
+    period: impl Fn(&PollIntervals) -> TokioDuration,
+) {
+    let period = period(&config.current().poll_intervals);
+    if interval.period() != period {
+        log::info!("Changing the poll interval to {period:?}");
+        *interval = interval_at(Instant::now() + period, period);
+    }
+}
 
-pub async fn session_worker(client: Arc<Mutex<InfoCarClient>>) {
+pub async fn session_worker(account: Arc<Account>, shutdown: CancellationToken) {
+    let client = &account.client;
     // A margin is used to refresh the token while it's still valid
     let token_refresh_margin = ChronoDuration::minutes(5);
     let refresh_retry_timeout = ChronoDuration::seconds(15);
+    // Without the expire date the token is refreshed right away
     let mut expire_date = client
         .lock()
         .await
         .get_token_expire_date()
-        .expect("Token expire date is empty");
+        .unwrap_or_else(Utc::now);
     log::trace!("Got the token expire date ({expire_date})");
 
     loop {
-        let duration = expire_date - Utc::now() - token_refresh_margin;
         let token_refresh_date = expire_date - token_refresh_margin;
+        // The token may already be past the margin, e.g. after a slow refresh
+        let duration = (token_refresh_date - Utc::now()).max(ChronoDuration::zero());
         log::info!(
             "JWT will be refreshed on {} (in {} seconds)",
             token_refresh_date,
             duration.num_seconds()
         );
-        sleep(TokioDuration::from_secs(
-            duration
-                .num_seconds()
-                .try_into()
-                .expect("Could not convert i64 TimeDelta to a u64"),
-        ))
-        .await;
+        let sleep = sleep(duration.to_std().unwrap_or_default());
+        if shutdown.run_until_cancelled(sleep).await.is_none() {
+            return;
+        }
 
         log::info!("Refreshing the token...");
         expire_date = match client.lock().await.refresh_token().await {
-            Ok(v) => v,
+            Ok(v) => {
+                account.health.token_refreshed(v);
+                v
+            }
             Err(err) => {
                 log::error!("Failed refresh the token. The attempt will be repeated in {} seconds. Error: {}", refresh_retry_timeout.num_seconds(), err);
                 Utc::now() + refresh_retry_timeout
@@ -49,23 +76,68 @@ pub async fn session_worker(client: Arc<Mutex<InfoCarClient>>) {
     }
 }
 
-pub async fn scheduler(client: Arc<Mutex<InfoCarClient>>, bot: Arc<Bot>, chat_id: ChatId) {
+pub async fn scheduler(account: Arc<Account>, config: SharedConfig, shutdown: CancellationToken) {
+    let client = &account.client;
     let mut last_exam_id = "".to_owned();
-    let mut interval = interval(TokioDuration::from_secs(15));
+    // Ids of the exams seen in the last poll, `None` before the first one
+    let mut seen_exams: Option<HashSet<String>> = None;
+    let mut interval = interval(config.current().poll_intervals.exams.to_std());
     loop {
-        interval.tick().await;
-        let closest_exam = match client.lock().await.get_nearest_exams(1).await {
-            Ok(mut v) => v.pop().unwrap(),
+        if shutdown
+            .run_until_cancelled(interval.tick())
+            .await
+            .is_none()
+        {
+            return;
+        }
+        follow_config(&mut interval, &config, |intervals| intervals.exams.to_std());
+        account.health.beat("scheduler", interval.period());
+        // The client is unlocked before handling the result, which locks it again
+        let result = client.lock().await.get_nearest_exams(usize::MAX).await;
+        let closest_exam = match result {
+            Ok(exams) => {
+                account.health.poll_succeeded();
+                record_poll_metrics(&account, &exams).await;
+                record_new_exams(&account, &mut seen_exams, &exams).await;
+                match exams.into_iter().next() {
+                    Some(exam) => exam,
+                    None => continue,
+                }
+            }
+            Err(GetExamsError::NoExamsError) => {
+                account.health.poll_succeeded();
+                record_poll_metrics(&account, &[]).await;
+                seen_exams = Some(HashSet::new());
+                log::trace!("[{}] No exams meet the criteria", account.name);
+                continue;
+            }
             Err(err) => {
+                account.health.poll_failed(err.to_string());
+                counter!("easycar_scheduler_polls_total", "account" => account.name.clone(), "result" => "error")
+                    .increment(1);
                 if let GetExamsError::GenericClientError(EnrollError::GenericEndpointError(
                     generic_error,
                 )) = &err
                 {
-                    if generic_error.0.first().expect("Empty vector").code == "invalid_token" {
-                        bot.send_message(chat_id, "The token was invalid reloging...")
-                            .await
-                            .unwrap();
-                        client.lock().await.refresh_token().await.unwrap();
+                    if generic_error
+                        .0
+                        .first()
+                        .is_some_and(|error| error.code == "invalid_token")
+                    {
+                        let _ = account
+                            .notify(Notification::new(
+                                Event::Error,
+                                &account.name,
+                                account.language().text(Text::InvalidToken),
+                            ))
+                            .await;
+                        match client.lock().await.refresh_token().await {
+                            Ok(expires) => account.health.token_refreshed(expires),
+                            Err(err) => log::error!(
+                                "[{}] Failed to refresh the invalid token: {err}",
+                                account.name
+                            ),
+                        }
                     }
                 }
                 log::error!(
@@ -74,36 +146,677 @@ pub async fn scheduler(client: Arc<Mutex<InfoCarClient>>, bot: Arc<Bot>, chat_id
                         .map(|src| format!(". Source: {src}"))
                         .unwrap_or("".to_owned())
                 );
-                bot.send_message(chat_id, format!("Error: {err}"))
-                    .await
-                    .unwrap();
-                sleep(TokioDuration::from_secs(15)).await;
+                let message = config.current().templates.render(
+                    Text::Error,
+                    account.language(),
+                    &[("error", &html::escape(&err.to_string()))],
+                );
+                let _ = account
+                    .notify(Notification::new(Event::Error, &account.name, message))
+                    .await;
+                let sleep = sleep(TokioDuration::from_secs(15));
+                if shutdown.run_until_cancelled(sleep).await.is_none() {
+                    return;
+                }
                 continue;
             }
         };
 
-        if closest_exam.id == last_exam_id {
+        if closest_exam.exam.id == last_exam_id {
             log::trace!("No change...");
             continue;
         }
 
-        last_exam_id = closest_exam.id;
+        last_exam_id = closest_exam.exam.id.clone();
 
-        let duration = date_from_string(&closest_exam.date)
-            .signed_duration_since(Utc::now())
-            .num_days();
-
-        let date = readable_date_from_string(closest_exam.date);
+        let word_name = match account.client.lock().await.watched_words().await {
+            Ok(words) => words
+                .into_iter()
+                .find(|word| word.id == closest_exam.word_id)
+                .map(|word| word.name),
+            Err(err) => {
+                log::warn!("Failed to get the WORD details: {err}");
+                None
+            }
+        }
+        .unwrap_or_else(|| closest_exam.word_id.to_string());
 
-        let exam_message = format!(
-            "New exam is available! The next exam date is {} (in <b>{}</b> days) (ID: <code>{}</code>)",
-            date, duration, &last_exam_id
+        let language = account.language();
+        let exam_message = config.current().templates.render(
+            Text::NewExam,
+            language,
+            &[
+                ("exam_id", &last_exam_id),
+                ("date", &language.date_from_string(&closest_exam.exam.date)),
+                ("days", &days_until(&closest_exam.exam.date)),
+                ("kind", &language.exam_kind(closest_exam.kind)),
+                ("category", &closest_exam.category),
+                ("word_id", &closest_exam.word_id),
+                ("word_name", &html::escape(&word_name)),
+                ("price", &closest_exam.exam.amount),
+                ("places", &closest_exam.exam.places),
+            ],
         );
 
-        log::info!("{exam_message}");
-        bot.send_message(chat_id, exam_message)
-            .parse_mode(ParseMode::Html)
+        log::info!("[{}] {exam_message}", account.name);
+        let _ = account
+            .notify(Notification::new(
+                Event::NewExam,
+                &account.name,
+                exam_message,
+            ))
+            .await;
+    }
+}
+
+/// Updates the poll count, the free exams per WORD and category and the days until the closest one
+async fn record_poll_metrics(account: &Account, exams: &[FoundExam]) {
+    counter!("easycar_scheduler_polls_total", "account" => account.name.clone(), "result" => "ok")
+        .increment(1);
+
+    let criteria = account.client.lock().await.criteria().clone();
+    for word_id in &criteria.words {
+        for category in &criteria.categories {
+            let free = exams
+                .iter()
+                .filter(|found| found.word_id == *word_id && found.category == *category)
+                .count();
+            gauge!(
+                "easycar_free_exams",
+                "account" => account.name.clone(),
+                "word" => word_id.to_string(),
+                "category" => category.to_string(),
+            )
+            .set(free as f64);
+        }
+    }
+
+    if let Some(date) = exams
+        .first()
+        .and_then(|found| try_date_from_string(&found.exam.date))
+    {
+        let days = (date - Utc::now()).num_seconds() as f64 / 86400.0;
+        gauge!("easycar_nearest_exam_days", "account" => account.name.clone()).set(days);
+    }
+}
+
+/// Adds the exams which weren't there in the previous poll to the history
+async fn record_new_exams(
+    account: &Account,
+    seen_exams: &mut Option<HashSet<String>>,
+    exams: &[FoundExam],
+) {
+    let ids = exams.iter().map(|found| found.exam.id.clone()).collect();
+    // Everything is new on the first poll, so it isn't recorded
+    let Some(previous) = seen_exams.replace(ids) else {
+        return;
+    };
+    let new_exams = exams
+        .iter()
+        .filter(|found| !previous.contains(&found.exam.id))
+        .cloned()
+        .collect::<Vec<_>>();
+    if new_exams.is_empty() {
+        return;
+    }
+    if let Err(err) = account
+        .state
+        .lock()
+        .await
+        .update(|state| state.record_exams(new_exams, Utc::now()))
+        .await
+    {
+        log::error!("[{}] Failed to save the exam history: {err}", account.name);
+    }
+}
+
+/// The date of the practice exam of the reservation, or of the theory one if there is none
+fn exam_date(exam: &ReservationExam) -> Option<&str> {
+    exam.practice
+        .as_ref()
+        .or(exam.theory.as_ref())
+        .map(|exam| exam.date.as_str())
+}
+
+pub(crate) fn readable_exam_date(exam: &ReservationExam, language: Language) -> String {
+    exam_date(exam)
+        .map(|date| language.date_from_string(date))
+        .unwrap_or(language.text(Text::UnknownDate).to_owned())
+}
+
+/// Number of days until the exam of the reservation, or `?` if it's unknown
+fn days_until_exam(exam: &ReservationExam) -> String {
+    exam_date(exam).map(days_until).unwrap_or("?".to_owned())
+}
+
+async fn status_change_message(
+    client: &Arc<Mutex<InfoCarClient>>,
+    item: &ReservationListItem,
+    previous: Option<&PossibleStatuses>,
+    language: Language,
+    templates: &TemplatesConfig,
+) -> String {
+    let mut message = templates.render(
+        Text::StatusChange,
+        language,
+        &[
+            ("reservation_id", &item.id),
+            (
+                "word_name",
+                &html::escape(&item.exam.organization_unit_name),
+            ),
+            ("date", &readable_exam_date(&item.exam, language)),
+            ("days", &days_until_exam(&item.exam)),
+            (
+                "previous",
+                &previous
+                    .map(|status| language.status(status))
+                    .unwrap_or(language.text(Text::NewStatus).to_owned()),
+            ),
+            ("status", &language.status(&item.status.status)),
+        ],
+    );
+    let status_message = |message: &str| {
+        language.format(Text::StatusMessage, &[("message", &html::escape(message))])
+    };
+
+    // The list doesn't contain the cancellation message, so the details have to be fetched
+    match client.lock().await.status(item.id.clone()).await {
+        Ok(status) => {
+            if let Some(reason) = status.status.message {
+                let _ = write!(message, "\n{}", status_message(&reason));
+            }
+            if let Some(reason) = status.cancellation_message {
+                let _ = write!(
+                    message,
+                    "\n{}",
+                    language.format(
+                        Text::CancellationReason,
+                        &[("reason", &html::escape(&reason))]
+                    )
+                );
+            }
+        }
+        Err(err) => {
+            log::warn!("Failed to get the status of reservation {}: {err}", item.id);
+            if let Some(reason) = &item.status.message {
+                let _ = write!(message, "\n{}", status_message(reason));
+            }
+        }
+    }
+
+    message
+}
+
+async fn export_calendar(
+    client: &Arc<Mutex<InfoCarClient>>,
+    path: &Path,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let calendar = client.lock().await.reservations_calendar().await?;
+    tokio::fs::write(path, calendar.to_string()).await?;
+    log::info!(
+        "Exported {} events to {}",
+        calendar.events.len(),
+        path.display()
+    );
+    Ok(())
+}
+
+pub async fn reservation_watcher(
+    account: Arc<Account>,
+    config: SharedConfig,
+    shutdown: CancellationToken,
+) {
+    let (client, state) = (&account.client, &account.state);
+    let mut interval = interval(config.current().poll_intervals.reservations.to_std());
+    let mut calendar_outdated = true;
+    loop {
+        if shutdown
+            .run_until_cancelled(interval.tick())
+            .await
+            .is_none()
+        {
+            return;
+        }
+        follow_config(&mut interval, &config, |intervals| {
+            intervals.reservations.to_std()
+        });
+        account
+            .health
+            .beat("reservation watcher", interval.period());
+        let reservations = match account.reservations(interval.period()).await {
+            Ok(v) => v,
+            Err(err) => {
+                log::error!("Got an error while retrieving reservations: {err}");
+                continue;
+            }
+        };
+
+        let (known_statuses, first_sync) = {
+            let state = state.lock().await;
+            (
+                state.state().reservation_statuses.clone(),
+                state.state().last_reservation_sync.is_none(),
+            )
+        };
+
+        for item in &reservations.items {
+            let previous = known_statuses.get(&item.id);
+            if previous == Some(&item.status.status) {
+                continue;
+            }
+
+            // Don't report every historical reservation on the first run
+            if !first_sync {
+                let templates = config.current().templates.clone();
+                let message =
+                    status_change_message(client, item, previous, account.language(), &templates)
+                        .await;
+
+                log::info!("[{}] {message}", account.name);
+                if account
+                    .notify(Notification::new(
+                        Event::StatusChange,
+                        &account.name,
+                        message,
+                    ))
+                    .await
+                    .is_err()
+                {
+                    // Try again on the next tick
+                    continue;
+                }
+            }
+
+            if let Err(err) = state
+                .lock()
+                .await
+                .update(|state| {
+                    state
+                        .reservation_statuses
+                        .insert(item.id.clone(), item.status.status.clone())
+                })
+                .await
+            {
+                log::error!("Failed to save the reservation status: {err}");
+            }
+            calendar_outdated = true;
+        }
+
+        let calendar_file = config
+            .current()
+            .account(&account.name)
+            .and_then(|account| account.calendar_file.clone());
+        if let (Some(path), true) = (calendar_file, calendar_outdated) {
+            calendar_outdated = match export_calendar(client, &path).await {
+                Ok(()) => false,
+                Err(err) => {
+                    log::error!("Failed to export the calendar: {err}");
+                    true
+                }
+            };
+        }
+
+        if let Err(err) = state
+            .lock()
             .await
-            .unwrap();
+            .update(|state| state.last_reservation_sync = Some(Utc::now()))
+            .await
+        {
+            log::error!("Failed to save the state: {err}");
+        }
+    }
+}
+
+/// Returns the date after which an unpaid reservation is lost
+pub fn payment_deadline(item: &ReservationListItem, window: ChronoDuration) -> DateTime<Utc> {
+    parse_api_timestamp(&item.status.timestamp).unwrap_or_else(Utc::now) + window
+}
+
+fn awaits_payment(item: &ReservationListItem) -> bool {
+    matches!(
+        item.status.status,
+        PossibleStatuses::Created | PossibleStatuses::PlaceReserved
+    )
+}
+
+pub async fn payment_reminder(
+    account: Arc<Account>,
+    config: SharedConfig,
+    shutdown: CancellationToken,
+) {
+    let state = &account.state;
+    let mut interval = interval(config.current().poll_intervals.payments.to_std());
+    loop {
+        if shutdown
+            .run_until_cancelled(interval.tick())
+            .await
+            .is_none()
+        {
+            return;
+        }
+        follow_config(&mut interval, &config, |intervals| {
+            intervals.payments.to_std()
+        });
+        account.health.beat("payment reminder", interval.period());
+        let reservations = match account.reservations(interval.period()).await {
+            Ok(v) => v,
+            Err(err) => {
+                log::error!("Got an error while retrieving reservations: {err}");
+                continue;
+            }
+        };
+
+        let reminders_config = config.current().reminders.clone();
+        let unpaid: Vec<&ReservationListItem> = reservations
+            .items
+            .iter()
+            .filter(|item| awaits_payment(item))
+            .collect();
+        let reminders = state.lock().await.state().payment_reminders.clone();
+
+        for item in &unpaid {
+            let reminder = reminders.get(&item.id).cloned();
+            let deadline = reminder
+                .as_ref()
+                .map(|reminder| reminder.deadline)
+                .unwrap_or_else(|| payment_deadline(item, reminders_config.payment_window.0));
+            let minutes_left = (deadline - Utc::now()).num_minutes();
+            let last_reminder = reminder.as_ref().and_then(|v| v.last_reminder);
+
+            let due_reminder = reminders_config
+                .payment
+                .iter()
+                .map(|reminder| reminder.0.num_minutes())
+                .filter(|minutes| minutes_left < *minutes)
+                .min();
+
+            if minutes_left < 0 {
+                continue;
+            }
+
+            let header = match &reminder {
+                None => Text::NewUnpaidReservation,
+                Some(_) => match due_reminder {
+                    // Skip if this (or a more urgent) reminder was already sent
+                    Some(due) if last_reminder.is_none_or(|last| due < last) => {
+                        Text::PaymentReminderHeader
+                    }
+                    _ => continue,
+                },
+            };
+
+            let language = account.language();
+            let message = config.current().templates.render(
+                Text::PaymentReminder,
+                language,
+                &[
+                    ("header", &language.text(header)),
+                    ("reservation_id", &item.id),
+                    (
+                        "word_name",
+                        &html::escape(&item.exam.organization_unit_name),
+                    ),
+                    ("date", &readable_exam_date(&item.exam, language)),
+                    ("days", &days_until_exam(&item.exam)),
+                    ("deadline", &language.date(deadline)),
+                    ("minutes", &minutes_left),
+                ],
+            );
+
+            log::info!("[{}] {message}", account.name);
+            if account
+                .notify(
+                    Notification::new(Event::Payment, &account.name, message)
+                        .with_keyboard(pay_keyboard(item.id.clone(), language)),
+                )
+                .await
+                .is_err()
+            {
+                continue;
+            }
+
+            if let Err(err) = state
+                .lock()
+                .await
+                .update(|state| {
+                    state.payment_reminders.insert(
+                        item.id.clone(),
+                        PaymentReminder {
+                            deadline,
+                            last_reminder: due_reminder.or(last_reminder),
+                        },
+                    )
+                })
+                .await
+            {
+                log::error!("Failed to save the payment reminder: {err}");
+            }
+        }
+
+        // Forget reservations which were paid, cancelled or expired
+        if reminders
+            .keys()
+            .any(|id| !unpaid.iter().any(|item| &item.id == id))
+        {
+            if let Err(err) = state
+                .lock()
+                .await
+                .update(|state| {
+                    state
+                        .payment_reminders
+                        .retain(|id, _| unpaid.iter().any(|item| &item.id == id))
+                })
+                .await
+            {
+                log::error!("Failed to save the state: {err}");
+            }
+        }
+    }
+}
+
+pub async fn exam_reminder(
+    account: Arc<Account>,
+    config: SharedConfig,
+    shutdown: CancellationToken,
+) {
+    let (client, state) = (&account.client, &account.state);
+    let mut interval = interval(config.current().poll_intervals.reminders.to_std());
+    loop {
+        if shutdown
+            .run_until_cancelled(interval.tick())
+            .await
+            .is_none()
+        {
+            return;
+        }
+        follow_config(&mut interval, &config, |intervals| {
+            intervals.reminders.to_std()
+        });
+        account.health.beat("exam reminder", interval.period());
+        let reservations = match account.reservations(interval.period()).await {
+            Ok(v) => v,
+            Err(err) => {
+                log::error!("Got an error while retrieving reservations: {err}");
+                continue;
+            }
+        };
+
+        let (offsets, sent_reminders) = {
+            let state = state.lock().await;
+            (
+                state
+                    .state()
+                    .exam_reminder_offsets
+                    .clone()
+                    .unwrap_or_else(|| config.current().reminders.exam_offsets()),
+                state.state().sent_exam_reminders.clone(),
+            )
+        };
+
+        let active: Vec<&ReservationListItem> = reservations
+            .items
+            .iter()
+            .filter(|item| item.status.status.is_active())
+            .collect();
+
+        for item in &active {
+            let Some(exam_date) = item
+                .exam
+                .practice
+                .as_ref()
+                .or(item.exam.theory.as_ref())
+                .and_then(|exam| try_date_from_string(&exam.date))
+            else {
+                continue;
+            };
+
+            let time_left = exam_date - Utc::now();
+            let Some(due_reminder) = offsets
+                .iter()
+                .copied()
+                .filter(|offset| time_left.num_minutes() < *offset)
+                .min()
+            else {
+                continue;
+            };
+            if time_left < ChronoDuration::zero()
+                || sent_reminders
+                    .get(&item.id)
+                    .is_some_and(|last| *last <= due_reminder)
+            {
+                continue;
+            }
+
+            let status = match client.lock().await.status(item.id.clone()).await {
+                Ok(v) => v,
+                Err(err) => {
+                    log::error!("Failed to get the status of reservation {}: {err}", item.id);
+                    continue;
+                }
+            };
+            let venue = status
+                .exam
+                .latitude
+                .parse::<f64>()
+                .ok()
+                .zip(status.exam.longitude.parse::<f64>().ok())
+                .map(|(latitude, longitude)| Venue {
+                    latitude,
+                    longitude,
+                    title: status.exam.organization_unit_name.clone(),
+                    address: status.exam.address.clone(),
+                });
+
+            let language = account.language();
+            let message = config.current().templates.render(
+                Text::ExamReminder,
+                language,
+                &[
+                    ("reservation_id", &item.id),
+                    (
+                        "word_name",
+                        &html::escape(&item.exam.organization_unit_name),
+                    ),
+                    ("address", &html::escape(&item.exam.address)),
+                    ("date", &readable_exam_date(&item.exam, language)),
+                    ("days", &days_until_exam(&item.exam)),
+                    ("time_left", &language.time_delta(time_left)),
+                    ("exam", &format_current_exam(status, language)),
+                    ("checklist", &language.text(Text::ExamChecklist)),
+                ],
+            );
+
+            log::info!("[{}] {message}", account.name);
+            let mut notification = Notification::new(Event::ExamReminder, &account.name, message);
+            if let Some(venue) = venue {
+                notification = notification.with_venue(venue);
+            }
+            if account.notify(notification).await.is_err() {
+                continue;
+            }
+
+            if let Err(err) = state
+                .lock()
+                .await
+                .update(|state| {
+                    state
+                        .sent_exam_reminders
+                        .insert(item.id.clone(), due_reminder)
+                })
+                .await
+            {
+                log::error!("Failed to save the exam reminder: {err}");
+            }
+        }
+
+        // Forget reservations which are no longer active
+        if sent_reminders
+            .keys()
+            .any(|id| !active.iter().any(|item| &item.id == id))
+        {
+            if let Err(err) = state
+                .lock()
+                .await
+                .update(|state| {
+                    state
+                        .sent_exam_reminders
+                        .retain(|id, _| active.iter().any(|item| &item.id == id))
+                })
+                .await
+            {
+                log::error!("Failed to save the state: {err}");
+            }
+        }
+    }
+}
+
+/// Tells systemd that the service is ready and, if the watchdog is enabled, keeps pinging it while
+/// every account is live, so that a stuck daemon gets restarted
+#[cfg(unix)]
+pub async fn systemd_watchdog(
+    accounts: Arc<Vec<Arc<Account>>>,
+    config: SharedConfig,
+    shutdown: CancellationToken,
+) {
+    use sd_notify::NotifyState;
+
+    if let Err(err) = sd_notify::notify(false, &[NotifyState::Ready]) {
+        log::warn!("Failed to notify systemd: {err}");
+    }
+    let mut usec = 0;
+    if !sd_notify::watchdog_enabled(false, &mut usec) {
+        shutdown.cancelled().await;
+        let _ = sd_notify::notify(false, &[NotifyState::Stopping]);
+        return;
+    }
+    let period = TokioDuration::from_micros(usec / 2);
+    log::info!("Pinging the systemd watchdog every {period:?}");
+
+    let mut interval = interval(period);
+    loop {
+        if shutdown
+            .run_until_cancelled(interval.tick())
+            .await
+            .is_none()
+        {
+            let _ = sd_notify::notify(false, &[NotifyState::Stopping]);
+            return;
+        }
+        let config = config.current();
+        let problems = accounts
+            .iter()
+            .filter_map(|account| {
+                let report = account.health_report(&config);
+                (!report.live).then(|| format!("[{}] {}", account.name, report.problems.join(", ")))
+            })
+            .collect::<Vec<_>>();
+        if !problems.is_empty() {
+            log::error!("Not pinging the systemd watchdog: {}", problems.join("; "));
+            continue;
+        }
+        if let Err(err) = sd_notify::notify(false, &[NotifyState::Watchdog]) {
+            log::warn!("Failed to ping the systemd watchdog: {err}");
+        }
     }
 }
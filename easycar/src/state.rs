@@ -0,0 +1,128 @@
+use std::{collections::HashMap, fs, io, path::PathBuf};
+
+use chrono::{DateTime, Utc};
+use info_car_api::types::PossibleStatuses;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{client::FoundExam, config::WatchConfig, i18n::Language};
+
+/// Maximum number of the remembered [`ExamAppearance`]s
+pub const EXAM_HISTORY_LIMIT: usize = 200;
+
+#[derive(Error, Debug)]
+pub enum StateError {
+    #[error("Failed to access the state file: {0}")]
+    IoError(#[from] io::Error),
+    #[error("Failed to parse the state file: {0}")]
+    ParseError(#[from] serde_json::Error),
+}
+
+/// Data which has to survive restarts of the daemon
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct State {
+    /// Last seen status of every reservation, keyed by the reservation id
+    #[serde(default)]
+    pub reservation_statuses: HashMap<String, PossibleStatuses>,
+    /// Date of the last successful synchronisation of the reservation list
+    #[serde(default)]
+    pub last_reservation_sync: Option<DateTime<Utc>>,
+    /// Unpaid reservations, keyed by the reservation id
+    #[serde(default)]
+    pub payment_reminders: HashMap<String, PaymentReminder>,
+    /// Minutes before the exam at which the reminders are sent. `None` means the defaults.
+    #[serde(default)]
+    pub exam_reminder_offsets: Option<Vec<i64>>,
+    /// The closest reminder (in minutes before the exam) which was already sent, keyed by the
+    /// reservation id
+    #[serde(default)]
+    pub sent_exam_reminders: HashMap<String, i64>,
+    /// Watch criteria changed from Telegram. `None` means the criteria from the configuration.
+    #[serde(default)]
+    pub watch_override: Option<WatchConfig>,
+    /// Exams which appeared while watching, the oldest first
+    #[serde(default)]
+    pub exam_history: Vec<ExamAppearance>,
+    /// Language chosen from Telegram. `None` means the language from the configuration.
+    #[serde(default)]
+    pub language: Option<Language>,
+}
+
+impl State {
+    /// Remembers the new exams, forgetting the oldest ones over the [`EXAM_HISTORY_LIMIT`]
+    pub fn record_exams(
+        &mut self,
+        exams: impl IntoIterator<Item = FoundExam>,
+        seen: DateTime<Utc>,
+    ) {
+        self.exam_history
+            .extend(exams.into_iter().map(|exam| ExamAppearance { seen, exam }));
+        let excess = self.exam_history.len().saturating_sub(EXAM_HISTORY_LIMIT);
+        self.exam_history.drain(..excess);
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ExamAppearance {
+    /// When the scheduler found the exam
+    pub seen: DateTime<Utc>,
+    pub exam: FoundExam,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PaymentReminder {
+    /// Date after which the unpaid reservation is lost
+    pub deadline: DateTime<Utc>,
+    /// The most urgent reminder (in minutes before the deadline) which was already sent
+    pub last_reminder: Option<i64>,
+}
+
+impl PaymentReminder {
+    pub fn new(deadline: DateTime<Utc>) -> Self {
+        Self {
+            deadline,
+            last_reminder: None,
+        }
+    }
+}
+
+/// A JSON file backed store of the [`State`]
+pub struct StateStore {
+    path: PathBuf,
+    state: State,
+}
+
+impl StateStore {
+    /// Loads the state from `path`. A missing file results in an empty state.
+    pub fn load(path: impl Into<PathBuf>) -> Result<Self, StateError> {
+        let path = path.into();
+        let state = match fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content)?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => State::default(),
+            Err(err) => Err(err)?,
+        };
+
+        log::debug!("Loaded state from {}", path.display());
+
+        Ok(Self { path, state })
+    }
+
+    pub fn state(&self) -> &State {
+        &self.state
+    }
+
+    /// Applies `f` to the state and writes the result to the disk
+    pub async fn update<T>(&mut self, f: impl FnOnce(&mut State) -> T) -> Result<T, StateError> {
+        let result = f(&mut self.state);
+        self.save().await?;
+        Ok(result)
+    }
+
+    pub async fn save(&self) -> Result<(), StateError> {
+        // Write to a temporary file first so that a crash can't leave a truncated state behind
+        let tmp_path = self.path.with_extension("tmp");
+        tokio::fs::write(&tmp_path, serde_json::to_string_pretty(&self.state)?).await?;
+        tokio::fs::rename(tmp_path, &self.path).await?;
+        Ok(())
+    }
+}
@@ -1,26 +1,203 @@
 use chrono::{DateTime, Duration, NaiveDateTime, TimeZone, Utc};
 use chrono_tz::Europe::Warsaw;
 
-pub fn date_from_string(timestamp: &str) -> DateTime<Utc> {
-    let naive_datetime = NaiveDateTime::parse_from_str(timestamp, "%Y-%m-%dT%H:%M:%S")
-        .expect(&format!("Failed to parse the timestamp '{}'", timestamp));
+/// Parses a timestamp in the Warsaw timezone. Returns `None` if the timestamp is malformed.
+pub fn try_date_from_string(timestamp: &str) -> Option<DateTime<Utc>> {
+    let naive_datetime = NaiveDateTime::parse_from_str(timestamp, "%Y-%m-%dT%H:%M:%S").ok()?;
     let datetime_cest: DateTime<chrono_tz::Tz> =
-        Warsaw.from_local_datetime(&naive_datetime).unwrap();
-    datetime_cest.with_timezone(&Utc)
+        Warsaw.from_local_datetime(&naive_datetime).earliest()?;
+    Some(datetime_cest.with_timezone(&Utc))
 }
 
-/// Returns a readable date with hours in bold and underlined. If it fails to convert the timestamp
-/// to a date returns the provided timestamp.
-pub fn readable_date_from_string(timestamp: String) -> String {
-    NaiveDateTime::parse_from_str(&timestamp, "%Y-%m-%dT%H:%M:%S")
-        .map(|date| date.format("<u><b>%H:%M</b></u>%e-%m-%Y").to_string())
-        .unwrap_or(timestamp)
+/// Number of days until the local API timestamp, or `?` if it's malformed
+pub fn days_until(timestamp: &str) -> String {
+    try_date_from_string(timestamp)
+        .map(|date| (date - Utc::now()).num_days().to_string())
+        .unwrap_or("?".to_owned())
 }
 
-pub fn readable_time_delta(duration: Duration) -> String {
-    let days = duration.num_days();
-    let hours = duration.num_hours() % 24;
-    let minutes = duration.num_minutes() % 60;
-    let seconds = duration.num_seconds() % 60;
-    format!("{days} days, {hours} hours, {minutes} minutes, {seconds} seconds")
+/// Parses a timestamp returned by the API, which may be either in RFC 3339 or in the local time
+pub fn parse_api_timestamp(timestamp: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(timestamp)
+        .map(|date| date.with_timezone(&Utc))
+        .ok()
+        .or_else(|| try_date_from_string(timestamp.split('.').next().unwrap_or(timestamp)))
+}
+
+/// Formats the date for terminal output
+pub fn plain_date(date: DateTime<Utc>) -> String {
+    date.with_timezone(&Warsaw)
+        .format("%Y-%m-%d %H:%M")
+        .to_string()
+}
+
+/// Formats the local API timestamp for terminal output
+pub fn plain_date_from_string(timestamp: &str) -> String {
+    NaiveDateTime::parse_from_str(timestamp, "%Y-%m-%dT%H:%M:%S")
+        .map(|date| date.format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or(timestamp.to_owned())
+}
+
+/// Strips the tags from the telegram flavoured HTML
+pub fn html_to_text(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            c if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Parses a short duration such as `15s`, `30m`, `2h`, `1d` or `1w`. Returns `None` if it's
+/// malformed or out of range.
+pub fn parse_duration(text: &str) -> Option<Duration> {
+    let text = text.trim();
+    let unit_start = text.find(|c: char| !c.is_ascii_digit())?;
+    let (value, unit) = text.split_at(unit_start);
+    let value: i64 = value.parse().ok()?;
+    match unit {
+        "s" => Duration::try_seconds(value),
+        "m" => Duration::try_minutes(value),
+        "h" => Duration::try_hours(value),
+        "d" => Duration::try_days(value),
+        "w" => Duration::try_weeks(value),
+        _ => None,
+    }
+}
+
+/// Formats a duration in the format accepted by [`parse_duration`]
+pub fn short_duration(duration: Duration) -> String {
+    if duration.num_seconds() % 60 != 0 {
+        return format!("{}s", duration.num_seconds());
+    }
+    match duration.num_minutes() {
+        m if m != 0 && m % (60 * 24 * 7) == 0 => format!("{}w", m / (60 * 24 * 7)),
+        m if m != 0 && m % (60 * 24) == 0 => format!("{}d", m / (60 * 24)),
+        m if m != 0 && m % 60 == 0 => format!("{}h", m / 60),
+        m => format!("{m}m"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(timestamp: &str) -> DateTime<Utc> {
+        timestamp.parse().unwrap()
+    }
+
+    #[test]
+    fn parses_durations() {
+        assert_eq!(parse_duration("15s"), Some(Duration::seconds(15)));
+        assert_eq!(parse_duration("30m"), Some(Duration::minutes(30)));
+        assert_eq!(parse_duration(" 2h "), Some(Duration::hours(2)));
+        assert_eq!(parse_duration("1d"), Some(Duration::days(1)));
+        assert_eq!(parse_duration("1w"), Some(Duration::weeks(1)));
+        assert_eq!(parse_duration("0m"), Some(Duration::zero()));
+    }
+
+    #[test]
+    fn rejects_malformed_durations() {
+        for text in [
+            "", "m", "15", "-5m", "+5m", "1.5h", "5M", "5 m", "5min", "1y", "m5",
+        ] {
+            assert_eq!(parse_duration(text), None, "{text:?}");
+        }
+    }
+
+    #[test]
+    fn rejects_overflowing_durations() {
+        assert_eq!(parse_duration("99999999999999999999s"), None);
+        assert_eq!(parse_duration("9999999999999999w"), None);
+        assert_eq!(parse_duration(&format!("{}s", i64::MAX)), None);
+    }
+
+    #[test]
+    fn formats_short_durations() {
+        assert_eq!(short_duration(Duration::seconds(90)), "90s");
+        assert_eq!(short_duration(Duration::minutes(61)), "61m");
+        assert_eq!(short_duration(Duration::hours(36)), "36h");
+        assert_eq!(short_duration(Duration::days(3)), "3d");
+        assert_eq!(short_duration(Duration::days(14)), "2w");
+        assert_eq!(short_duration(Duration::zero()), "0m");
+        for text in ["45s", "30m", "2h", "1d", "1w"] {
+            assert_eq!(short_duration(parse_duration(text).unwrap()), text);
+        }
+    }
+
+    #[test]
+    fn parses_local_timestamps() {
+        // CEST, UTC+2
+        assert_eq!(
+            try_date_from_string("2025-05-12T08:30:00"),
+            Some(utc("2025-05-12T06:30:00Z"))
+        );
+        // CET, UTC+1
+        assert_eq!(
+            try_date_from_string("2025-01-12T08:30:00"),
+            Some(utc("2025-01-12T07:30:00Z"))
+        );
+        // The hour skipped when the clocks go forward
+        assert_eq!(try_date_from_string("2025-03-30T02:30:00"), None);
+        // The earlier of the repeated hours when the clocks go back
+        assert_eq!(
+            try_date_from_string("2025-10-26T02:30:00"),
+            Some(utc("2025-10-26T00:30:00Z"))
+        );
+        assert_eq!(try_date_from_string(""), None);
+        assert_eq!(try_date_from_string("2025-05-12"), None);
+        assert_eq!(try_date_from_string("2025-05-12 08:30:00"), None);
+    }
+
+    #[test]
+    fn parses_api_timestamps() {
+        assert_eq!(
+            parse_api_timestamp("2025-05-12T08:30:00Z"),
+            Some(utc("2025-05-12T08:30:00Z"))
+        );
+        assert_eq!(
+            parse_api_timestamp("2025-05-12T08:30:00+02:00"),
+            Some(utc("2025-05-12T06:30:00Z"))
+        );
+        // Local time, with or without the fraction of a second
+        assert_eq!(
+            parse_api_timestamp("2025-05-12T08:30:00"),
+            Some(utc("2025-05-12T06:30:00Z"))
+        );
+        assert_eq!(
+            parse_api_timestamp("2025-05-12T08:30:00.123456"),
+            Some(utc("2025-05-12T06:30:00Z"))
+        );
+        assert_eq!(parse_api_timestamp(""), None);
+        assert_eq!(parse_api_timestamp("yesterday"), None);
+    }
+
+    #[test]
+    fn formats_plain_dates() {
+        assert_eq!(plain_date(utc("2025-12-31T23:15:00Z")), "2026-01-01 00:15");
+        assert_eq!(
+            plain_date_from_string("2025-05-12T08:30:00"),
+            "2025-05-12 08:30"
+        );
+        assert_eq!(plain_date_from_string("unknown"), "unknown");
+        assert_eq!(days_until("unknown"), "?");
+    }
+
+    #[test]
+    fn strips_html() {
+        assert_eq!(
+            html_to_text("<b>Exam</b> in <u>WORD &amp; co</u> &lt;3"),
+            "Exam in WORD & co <3"
+        );
+        assert_eq!(html_to_text("&amp;lt;"), "&lt;");
+    }
 }
@@ -0,0 +1,178 @@
+use std::fmt::Write;
+
+use clap::ValueEnum;
+use info_car_api::types::WordCenters;
+use serde::Serialize;
+
+/// Output format of the WORD mapping
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, ValueEnum)]
+pub enum WordsFormat {
+    #[default]
+    Markdown,
+    Csv,
+    Json,
+}
+
+/// A WORD center joined with its province
+#[derive(Clone, Debug, Serialize)]
+pub struct WordEntry {
+    pub id: u32,
+    pub name: String,
+    pub address: String,
+    pub province: String,
+    pub latitude: String,
+    pub longitude: String,
+    pub offline: bool,
+}
+
+/// Returns the WORD centers sorted by their id
+pub fn word_entries(centers: &WordCenters) -> Vec<WordEntry> {
+    let mut entries: Vec<WordEntry> = centers
+        .words
+        .iter()
+        .map(|word| WordEntry {
+            id: word.id.get(),
+            name: word.name.clone(),
+            address: word.address.clone(),
+            province: centers
+                .provinces
+                .iter()
+                .find(|province| province.id == word.province_id)
+                .map(|province| province.name.clone())
+                .unwrap_or_default(),
+            latitude: word.latitude.clone(),
+            longitude: word.longitude.clone(),
+            offline: word.offline,
+        })
+        .collect();
+    entries.sort_by_key(|entry| entry.id);
+    entries
+}
+
+impl WordEntry {
+    fn columns(&self) -> [String; 7] {
+        [
+            self.id.to_string(),
+            self.name.clone(),
+            self.address.clone(),
+            self.province.clone(),
+            self.latitude.clone(),
+            self.longitude.clone(),
+            if self.offline { "yes" } else { "no" }.to_owned(),
+        ]
+    }
+}
+
+const HEADERS: [&str; 7] = [
+    "`OSK_ID`",
+    "WORD Name",
+    "WORD Address",
+    "Province",
+    "Latitude",
+    "Longitude",
+    "Offline",
+];
+
+/// Renders the table in the layout of `docs/WORD-mapping.md`
+fn render_markdown(entries: &[WordEntry]) -> String {
+    // A `|` in a cell would end it, so it's escaped before the widths are measured
+    let rows: Vec<[String; 7]> = entries
+        .iter()
+        .map(|entry| entry.columns().map(|cell| cell.replace('|', "\\|")))
+        .collect();
+    let widths: Vec<usize> = (0..HEADERS.len())
+        .map(|column| {
+            rows.iter()
+                .map(|row| row[column].chars().count())
+                .chain([HEADERS[column].chars().count()])
+                .max()
+                .unwrap_or_default()
+        })
+        .collect();
+
+    let line = |cells: Vec<String>| {
+        let cells: Vec<String> = cells
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{cell:<width$}"))
+            .collect();
+        format!("| {} |\n", cells.join(" | "))
+    };
+
+    let mut output = "# WORD to `OSK_ID` Mapping\n\n".to_owned();
+    output += &line(HEADERS.map(str::to_owned).to_vec());
+    output += &line(widths.iter().map(|width| "-".repeat(*width)).collect());
+    for row in rows {
+        output += &line(row.to_vec());
+    }
+    output
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+fn render_csv(entries: &[WordEntry]) -> String {
+    let mut output = "id,name,address,province,latitude,longitude,offline\n".to_owned();
+    for entry in entries {
+        let _ = writeln!(
+            output,
+            "{},{},{},{},{},{},{}",
+            entry.id,
+            csv_field(&entry.name),
+            csv_field(&entry.address),
+            csv_field(&entry.province),
+            csv_field(&entry.latitude),
+            csv_field(&entry.longitude),
+            entry.offline
+        );
+    }
+    output
+}
+
+/// Renders the WORD mapping in the given format
+pub fn render_words(
+    centers: &WordCenters,
+    format: WordsFormat,
+) -> Result<String, serde_json::Error> {
+    let entries = word_entries(centers);
+    Ok(match format {
+        WordsFormat::Markdown => render_markdown(&entries),
+        WordsFormat::Csv => render_csv(&entries),
+        WordsFormat::Json => serde_json::to_string_pretty(&entries)? + "\n",
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(name: &str) -> WordEntry {
+        WordEntry {
+            id: 1,
+            name: name.to_owned(),
+            address: "ul. Równa 19/21".to_owned(),
+            province: "pomorskie".to_owned(),
+            latitude: "54.35".to_owned(),
+            longitude: "18.6".to_owned(),
+            offline: false,
+        }
+    }
+
+    #[test]
+    fn escapes_pipes_in_markdown() {
+        let markdown = render_markdown(&[entry("WORD | Gdańsk"), entry("WORD Gdynia")]);
+        let lines: Vec<&str> = markdown.lines().skip(2).collect();
+
+        assert!(lines[2].contains("WORD \\| Gdańsk"));
+        // Every row has the same columns and width
+        for line in &lines {
+            assert_eq!(line.replace("\\|", "").matches('|').count(), 8, "{line}");
+            assert_eq!(line.chars().count(), lines[0].chars().count(), "{line}");
+        }
+    }
+}
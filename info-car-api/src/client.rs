@@ -4,7 +4,7 @@ use crate::error::*;
 use crate::types::*;
 
 use chrono::{DateTime, Duration, Utc};
-use reqwest::ClientBuilder;
+use reqwest::{ClientBuilder, RequestBuilder, Response};
 use scraper::{Html, Selector};
 use serde::Deserialize;
 
@@ -18,6 +18,45 @@ pub struct UserInfo {
     pub email: String,
 }
 
+/// Records the count, latency and outcome of the requests in the `info_car_*` metrics when built
+/// with the `metrics` feature
+trait InstrumentedSend {
+    async fn send_instrumented(self, endpoint: &'static str) -> reqwest::Result<Response>;
+}
+
+#[cfg(feature = "metrics")]
+impl InstrumentedSend for RequestBuilder {
+    async fn send_instrumented(self, endpoint: &'static str) -> reqwest::Result<Response> {
+        use metrics::{counter, histogram};
+
+        let start = std::time::Instant::now();
+        let result = self.send().await;
+        let outcome = match &result {
+            Ok(response) => match response.status().as_u16() {
+                200..=299 => "2xx",
+                300..=399 => "3xx",
+                401 => "unauthorized",
+                400..=499 => "4xx",
+                _ => "5xx",
+            },
+            Err(err) if err.is_timeout() => "timeout",
+            Err(_) => "connection",
+        };
+        histogram!("info_car_request_duration_seconds", "endpoint" => endpoint)
+            .record(start.elapsed());
+        counter!("info_car_requests_total", "endpoint" => endpoint, "outcome" => outcome)
+            .increment(1);
+        result
+    }
+}
+
+#[cfg(not(feature = "metrics"))]
+impl InstrumentedSend for RequestBuilder {
+    async fn send_instrumented(self, _endpoint: &'static str) -> reqwest::Result<Response> {
+        self.send().await
+    }
+}
+
 pub struct Client {
     client: reqwest::Client,
     token: Option<String>,
@@ -59,7 +98,7 @@ impl Client {
                 ("scope", "openid profile email resource.read"),
                 ("prompt", "none"),
             ])
-            .send()
+            .send_instrumented("authorize")
             .await?;
 
         log::debug!("{response:?}");
@@ -89,7 +128,7 @@ impl Client {
     }
 
     async fn get_csrf_token(&self, url: &str) -> Result<String, CsrfTokenError> {
-        let response = self.client.get(url).send().await?;
+        let response = self.client.get(url).send_instrumented("login_page").await?;
 
         log::debug!("{response:?}");
 
@@ -123,7 +162,7 @@ impl Client {
         self.client
             .post("https://info-car.pl/oauth2/login")
             .form(&form_params)
-            .send()
+            .send_instrumented("login")
             .await?;
 
         self.refresh_token().await?;
@@ -137,7 +176,7 @@ impl Client {
                 "https://info-car.pl/oauth2/endsession?id_token_hint={}",
                 self.get_token()?
             ))
-            .send()
+            .send_instrumented("logout")
             .await?;
         self.token = None;
         Ok(())
@@ -148,7 +187,7 @@ impl Client {
             .client
             .get("https://info-car.pl/oauth2/userinfo")
             .bearer_auth(self.get_token()?)
-            .send()
+            .send_instrumented("user_info")
             .await?
             .json()
             .await?)
@@ -159,7 +198,7 @@ impl Client {
             .client
             .get("https://info-car.pl/api/word/reservations")
             .bearer_auth(self.get_token()?)
-            .send()
+            .send_instrumented("reservations")
             .await?;
 
         log::debug!("{response:?}");
@@ -172,7 +211,7 @@ impl Client {
             .client
             .get("https://info-car.pl/api/word/word-centers")
             .bearer_auth(self.get_token()?)
-            .send()
+            .send_instrumented("word_centers")
             .await?;
 
         log::debug!("{response:?}");
@@ -190,7 +229,7 @@ impl Client {
                 "https://info-car.pl/api/word/word-centers/reschedule-enabled/{word_id}"
             ))
             .bearer_auth(self.get_token()?)
-            .send()
+            .send_instrumented("reschedule_enabled")
             .await?;
 
         log::debug!("{response:?}");
@@ -220,7 +259,7 @@ impl Client {
             .put("https://info-car.pl/api/word/word-centers/exam-schedule")
             .bearer_auth(self.get_token()?)
             .json(&map)
-            .send()
+            .send_instrumented("exam_schedule")
             .await?;
 
         log::debug!("{response:?}");
@@ -240,7 +279,7 @@ impl Client {
             .post("https://info-car.pl/api/word/reservations")
             .bearer_auth(self.get_token()?)
             .json(&reservation)
-            .send()
+            .send_instrumented("new_reservation")
             .await?;
 
         log::debug!("{response:?}");
@@ -265,7 +304,7 @@ impl Client {
                 "https://info-car.pl/api/word/reservations/{reservation_id}"
             ))
             .bearer_auth(self.get_token()?)
-            .send()
+            .send_instrumented("reservation_status")
             .await?;
 
         log::debug!("{response:?}");
@@ -286,7 +325,7 @@ impl Client {
                 "https://info-car.pl/api/word/reservations/{reservation_id}/cancel"
             ))
             .bearer_auth(self.get_token()?)
-            .send()
+            .send_instrumented("cancel_reservation")
             .await?;
 
         log::debug!("{response:?}");
@@ -314,7 +353,7 @@ impl Client {
             ))
             .json(&request)
             .bearer_auth(self.get_token()?)
-            .send()
+            .send_instrumented("blik_payment")
             .await?;
 
         log::debug!("{response:?}");
@@ -0,0 +1,409 @@
+//! A minimal [iCalendar](https://datatracker.ietf.org/doc/html/rfc5545) serializer for the exams
+//! and reservations.
+use core::fmt;
+
+use chrono::{Duration, NaiveDateTime, Utc};
+
+use crate::types::{Exam, ReservationListItem, ReservationStatus, TheoryOrPracticeExam, Word};
+
+/// Format of the dates returned by the API
+const API_DATE_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+/// All dates returned by the API are in the Polish local time
+const TIMEZONE: &str = "Europe/Warsaw";
+const VTIMEZONE: &str = "BEGIN:VTIMEZONE
+TZID:Europe/Warsaw
+BEGIN:DAYLIGHT
+TZOFFSETFROM:+0100
+TZOFFSETTO:+0200
+TZNAME:CEST
+DTSTART:19700329T020000
+RRULE:FREQ=YEARLY;BYMONTH=3;BYDAY=-1SU
+END:DAYLIGHT
+BEGIN:STANDARD
+TZOFFSETFROM:+0200
+TZOFFSETTO:+0100
+TZNAME:CET
+DTSTART:19701025T030000
+RRULE:FREQ=YEARLY;BYMONTH=10;BYDAY=-1SU
+END:STANDARD
+END:VTIMEZONE";
+
+/// Length of an exam used when the API doesn't provide one
+const DEFAULT_EXAM_LENGTH: Duration = Duration::hours(1);
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CalendarEvent {
+    pub uid: String,
+    pub summary: String,
+    pub description: Option<String>,
+    pub location: Option<String>,
+    /// Latitude and longitude of the location
+    pub geo: Option<(f64, f64)>,
+    /// Local (Warsaw) start time of the event
+    pub start: NaiveDateTime,
+    pub duration: Duration,
+}
+
+fn parse_api_date(date: &str) -> Option<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(date, API_DATE_FORMAT).ok()
+}
+
+fn parse_geo(latitude: &str, longitude: &str) -> Option<(f64, f64)> {
+    Some((latitude.parse().ok()?, longitude.parse().ok()?))
+}
+
+fn exam_kind(
+    theory: &Option<TheoryOrPracticeExam>,
+    practice: &Option<TheoryOrPracticeExam>,
+) -> &'static str {
+    match (theory, practice) {
+        (Some(_), Some(_)) => "Theory and practice exam",
+        (Some(_), None) => "Theory exam",
+        _ => "Practice exam",
+    }
+}
+
+impl CalendarEvent {
+    pub fn from_reservation_status(status: &ReservationStatus) -> Option<Self> {
+        let exam = &status.exam;
+        let room = exam
+            .theory
+            .as_ref()
+            .or(exam.pracitce.as_ref())
+            .and_then(|v| v.room.as_deref());
+
+        let mut description = format!(
+            "Reservation: {}\nStatus: {}",
+            status.id, status.status.status
+        );
+        if let Some(room) = room {
+            description.push_str(&format!("\nRoom: {room}"));
+        }
+        if let Some(vehicle) = &exam.osk_vehicle_number {
+            description.push_str(&format!("\nOSK vehicle: {vehicle}"));
+        }
+
+        Some(Self {
+            uid: format!("reservation-{}@info-car.pl", status.id),
+            summary: format!(
+                "{} ({}) - {}",
+                exam_kind(&exam.theory, &exam.pracitce),
+                exam.category,
+                exam.organization_unit_name
+            ),
+            description: Some(description),
+            location: Some(exam.address.clone()),
+            geo: parse_geo(&exam.latitude, &exam.longitude),
+            start: parse_api_date(&exam.exam_date)?,
+            duration: DEFAULT_EXAM_LENGTH,
+        })
+    }
+
+    /// Creates an event for every part (theory and practice) of a reservation from the list. Unlike
+    /// [`Self::from_reservation_status`] it doesn't need a request per reservation, but the
+    /// events lack the room and the coordinates.
+    pub fn from_reservation(item: &ReservationListItem) -> Vec<Self> {
+        let exam = &item.exam;
+        [
+            ("theory", "Theory", &exam.theory),
+            ("practice", "Practice", &exam.practice),
+        ]
+        .into_iter()
+        .filter_map(|(part, name, details)| {
+            let details = details.as_ref()?;
+            // Cancelled reservations are kept in the list, so they are marked
+            let inactive = if item.status.status.is_active() {
+                String::new()
+            } else {
+                format!("{}: ", item.status.status)
+            };
+            let mut description = format!(
+                "Reservation: {}\nStatus: {}\nExam: {}",
+                item.id, item.status.status, details.exam_id
+            );
+            if let Some(info) = &details.additional_info {
+                description.push_str(&format!("\n{info}"));
+            }
+
+            Some(Self {
+                uid: format!("reservation-{}-{part}@info-car.pl", item.id),
+                summary: format!(
+                    "{inactive}{name} exam ({}) - {}",
+                    exam.category, exam.organization_unit_name
+                ),
+                description: Some(description),
+                location: Some(exam.address.clone()),
+                geo: None,
+                start: parse_api_date(&details.date)?,
+                duration: DEFAULT_EXAM_LENGTH,
+            })
+        })
+        .collect()
+    }
+
+    /// Creates an event for a free theory exam slot held in the `word` center
+    pub fn from_theory_exam(exam: &Exam, word: Option<&Word>) -> Option<Self> {
+        Self::from_exam(exam, "Free theory exam slot", word)
+    }
+
+    /// Creates an event for a free practice exam slot held in the `word` center
+    pub fn from_practice_exam(exam: &Exam, word: Option<&Word>) -> Option<Self> {
+        Self::from_exam(exam, "Free practice exam slot", word)
+    }
+
+    fn from_exam(exam: &Exam, summary: &str, word: Option<&Word>) -> Option<Self> {
+        let mut description = format!(
+            "Exam: {}\nFree places: {}\nPrice: {} PLN",
+            exam.id, exam.places, exam.amount
+        );
+        if let Some(info) = &exam.additional_info {
+            description.push_str(&format!("\n{info}"));
+        }
+
+        Some(Self {
+            uid: format!("exam-{}@info-car.pl", exam.id),
+            summary: match word {
+                Some(word) => format!("{summary} - {}", word.name),
+                None => summary.to_owned(),
+            },
+            description: Some(description),
+            location: word.map(|word| word.address.clone()),
+            geo: word.and_then(|word| parse_geo(&word.latitude, &word.longitude)),
+            start: parse_api_date(&exam.date)?,
+            duration: DEFAULT_EXAM_LENGTH,
+        })
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Calendar {
+    pub events: Vec<CalendarEvent>,
+}
+
+impl Calendar {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl FromIterator<CalendarEvent> for Calendar {
+    fn from_iter<T: IntoIterator<Item = CalendarEvent>>(iter: T) -> Self {
+        Self {
+            events: iter.into_iter().collect(),
+        }
+    }
+}
+
+/// Escapes the special characters of a TEXT value
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace(['\r', '\n'], "\\n")
+}
+
+/// Writes a content line folded to 75 octets, as required by the RFC
+fn write_line(f: &mut fmt::Formatter<'_>, line: &str) -> fmt::Result {
+    let mut length = 0;
+    for c in line.chars() {
+        if length + c.len_utf8() > 75 {
+            f.write_str("\r\n ")?;
+            length = 1;
+        }
+        write!(f, "{c}")?;
+        length += c.len_utf8();
+    }
+    f.write_str("\r\n")
+}
+
+impl fmt::Display for Calendar {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let stamp = Utc::now().format("%Y%m%dT%H%M%SZ");
+
+        write_line(f, "BEGIN:VCALENDAR")?;
+        write_line(f, "VERSION:2.0")?;
+        write_line(f, "PRODID:-//easycar//info-car-api//EN")?;
+        write_line(f, "CALSCALE:GREGORIAN")?;
+        for line in VTIMEZONE.lines() {
+            write_line(f, line)?;
+        }
+        for event in &self.events {
+            write_line(f, "BEGIN:VEVENT")?;
+            write_line(f, &format!("UID:{}", event.uid))?;
+            write_line(f, &format!("DTSTAMP:{stamp}"))?;
+            write_line(
+                f,
+                &format!(
+                    "DTSTART;TZID={TIMEZONE}:{}",
+                    event.start.format("%Y%m%dT%H%M%S")
+                ),
+            )?;
+            write_line(
+                f,
+                &format!(
+                    "DTEND;TZID={TIMEZONE}:{}",
+                    (event.start + event.duration).format("%Y%m%dT%H%M%S")
+                ),
+            )?;
+            write_line(f, &format!("SUMMARY:{}", escape(&event.summary)))?;
+            if let Some(description) = &event.description {
+                write_line(f, &format!("DESCRIPTION:{}", escape(description)))?;
+            }
+            if let Some(location) = &event.location {
+                write_line(f, &format!("LOCATION:{}", escape(location)))?;
+            }
+            if let Some((latitude, longitude)) = event.geo {
+                write_line(f, &format!("GEO:{latitude};{longitude}"))?;
+            }
+            write_line(f, "END:VEVENT")?;
+        }
+        write_line(f, "END:VCALENDAR")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Writes a single content line with [`write_line`]
+    struct Line<'a>(&'a str);
+
+    impl fmt::Display for Line<'_> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write_line(f, self.0)
+        }
+    }
+
+    fn unfold(text: &str) -> String {
+        text.replace("\r\n ", "")
+    }
+
+    #[test]
+    fn escapes_special_characters() {
+        assert_eq!(escape("a;b,c\\d"), r"a\;b\,c\\d");
+        assert_eq!(
+            escape("one\ntwo\r\nthree\rfour"),
+            "one\\ntwo\\nthree\\nfour"
+        );
+        assert_eq!(escape("plain text"), "plain text");
+    }
+
+    #[test]
+    fn short_lines_are_not_folded() {
+        assert_eq!(Line("SUMMARY:Exam").to_string(), "SUMMARY:Exam\r\n");
+        let line = "X".repeat(75);
+        assert_eq!(Line(&line).to_string(), format!("{line}\r\n"));
+    }
+
+    #[test]
+    fn long_lines_are_folded_to_75_octets() {
+        let line = format!("DESCRIPTION:{}", "a".repeat(200));
+        let written = Line(&line).to_string();
+
+        assert!(written.ends_with("\r\n"));
+        assert!(written
+            .trim_end_matches("\r\n")
+            .split("\r\n")
+            .all(|part| part.len() <= 75));
+        assert_eq!(unfold(&written), format!("{line}\r\n"));
+    }
+
+    #[test]
+    fn folding_doesnt_split_characters() {
+        let line = format!("LOCATION:{}", "żółć".repeat(40));
+        let written = Line(&line).to_string();
+
+        for part in written.trim_end_matches("\r\n").split("\r\n") {
+            assert!(part.len() <= 75, "{part:?} is longer than 75 octets");
+        }
+        assert_eq!(unfold(&written), format!("{line}\r\n"));
+    }
+
+    #[test]
+    fn reservations_from_the_list_become_events() {
+        use std::num::NonZeroU32;
+
+        use crate::types::{Candidate, PossibleStatuses, ReservationExam, Status};
+
+        let part = |exam_id: &str, date: &str| TheoryOrPracticeExam {
+            exam_id: exam_id.to_owned(),
+            date: date.to_owned(),
+            additional_info: None,
+            room: None,
+        };
+        let mut item = ReservationListItem {
+            awaiting_reschedule: false,
+            status: Status {
+                status: PossibleStatuses::SignupConfirmed,
+                timestamp: "2025-05-01T10:00:00".to_owned(),
+                message: None,
+            },
+            exam: ReservationExam {
+                organization_unit_id: NonZeroU32::MIN,
+                organization_unit_name: "WORD Gdańsk".to_owned(),
+                theory: Some(part("t-1", "2025-05-12T08:30:00")),
+                practice: Some(part("p-1", "2025-05-13T10:00:00")),
+                category: Default::default(),
+                address: "ul. Równa 19/21, Gdańsk".to_owned(),
+            },
+            candidate: Candidate::default(),
+            id: "r-1".to_owned(),
+        };
+
+        let events = CalendarEvent::from_reservation(&item);
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].uid, "reservation-r-1-theory@info-car.pl");
+        assert_eq!(events[0].summary, "Theory exam (B) - WORD Gdańsk");
+        assert_eq!(
+            events[0].start,
+            parse_api_date("2025-05-12T08:30:00").unwrap()
+        );
+        assert_eq!(events[1].uid, "reservation-r-1-practice@info-car.pl");
+        assert_eq!(
+            events[1].description.as_deref(),
+            Some("Reservation: r-1\nStatus: Signup Confirmed\nExam: p-1")
+        );
+        assert_eq!(
+            events[1].location.as_deref(),
+            Some("ul. Równa 19/21, Gdańsk")
+        );
+
+        item.status.status = PossibleStatuses::Cancelled;
+        item.exam.theory = None;
+        item.exam.practice = Some(part("p-1", "not a date"));
+        assert!(CalendarEvent::from_reservation(&item).is_empty());
+
+        item.exam.practice = Some(part("p-1", "2025-05-13T10:00:00"));
+        let events = CalendarEvent::from_reservation(&item);
+        assert_eq!(
+            events[0].summary,
+            "Cancelled: Practice exam (B) - WORD Gdańsk"
+        );
+    }
+
+    #[test]
+    fn calendar_lines_are_folded_and_escaped() {
+        let calendar = Calendar {
+            events: vec![CalendarEvent {
+                uid: "exam-1@info-car.pl".to_owned(),
+                summary: "Free practice exam slot - WORD Gdańsk".to_owned(),
+                description: Some(format!("Exam: 1\n{}", "Notes, more notes; ".repeat(10))),
+                location: Some("ul. Równa 19/21, Gdańsk".to_owned()),
+                geo: Some((54.35, 18.6)),
+                start: parse_api_date("2025-05-12T08:30:00").unwrap(),
+                duration: DEFAULT_EXAM_LENGTH,
+            }],
+        };
+        let ics = calendar.to_string();
+
+        assert!(ics.split("\r\n").all(|line| line.len() <= 75));
+        let unfolded = unfold(&ics);
+        assert!(unfolded.contains("DTSTART;TZID=Europe/Warsaw:20250512T083000\r\n"));
+        assert!(unfolded.contains("DTEND;TZID=Europe/Warsaw:20250512T093000\r\n"));
+        assert!(unfolded.contains("LOCATION:ul. Równa 19/21\\, Gdańsk\r\n"));
+        assert!(unfolded.contains(r"DESCRIPTION:Exam: 1\nNotes\, more notes\; "));
+        assert!(unfolded.starts_with("BEGIN:VCALENDAR\r\n"));
+        assert!(unfolded.ends_with("END:VCALENDAR\r\n"));
+    }
+}
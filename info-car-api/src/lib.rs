@@ -1,4 +1,5 @@
 pub mod client;
 pub mod error;
+pub mod ical;
 pub mod types;
 pub mod utils;
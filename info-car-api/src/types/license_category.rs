@@ -1,4 +1,4 @@
-use core::fmt;
+use core::{fmt, str::FromStr};
 use serde::{Deserialize, Serialize};
 
 #[derive(Clone, Debug, Default, Eq, PartialEq, Deserialize, Serialize)]
@@ -47,3 +47,31 @@ impl fmt::Display for LicenseCategory {
         write!(f, "{category}")
     }
 }
+
+impl FromStr for LicenseCategory {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let category = match s.to_uppercase().as_str() {
+            "A" => LicenseCategory::A,
+            "A1" => LicenseCategory::A1,
+            "A2" => LicenseCategory::A2,
+            "AM" => LicenseCategory::AM,
+            "B" => LicenseCategory::B,
+            "B1" => LicenseCategory::B1,
+            "BE" => LicenseCategory::BE,
+            "C" => LicenseCategory::C,
+            "C1" => LicenseCategory::C1,
+            "CE" => LicenseCategory::CE,
+            "C1E" => LicenseCategory::C1E,
+            "D" => LicenseCategory::D,
+            "D1" => LicenseCategory::D1,
+            "DE" => LicenseCategory::DE,
+            "D1E" => LicenseCategory::D1E,
+            "T" => LicenseCategory::T,
+            "PT" => LicenseCategory::PT,
+            _ => return Err(format!("unknown license category '{s}'")),
+        };
+        Ok(category)
+    }
+}
@@ -1,11 +1,11 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use serde_aux::field_attributes::deserialize_number_from_string;
 use std::num::NonZeroU32;
 
 use crate::types::{LicenseCategory, Status, TheoryOrPracticeExam};
 
 // TODO: Convert theory or pracitce to a type
-#[derive(Clone, Debug, Eq, PartialEq, Deserialize)]
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ReservationExam {
     #[serde(deserialize_with = "deserialize_number_from_string")]
@@ -17,13 +17,13 @@ pub struct ReservationExam {
     pub address: String,
 }
 
-#[derive(Clone, Debug, Default, Eq, PartialEq, Deserialize)]
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
 pub struct Candidate {
     pub firstname: String,
     pub lastname: String,
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct ReservationListItem {
     pub awaiting_reschedule: bool,
@@ -33,7 +33,7 @@ pub struct ReservationListItem {
     pub id: String,
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct ReservationList {
     pub items: Vec<ReservationListItem>,
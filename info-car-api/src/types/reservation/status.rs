@@ -1,18 +1,18 @@
 use core::fmt;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use serde_aux::field_attributes::deserialize_number_from_string;
 use std::num::NonZeroU32;
 
 use crate::types::{LicenseCategory, TheoryOrPracticeExam};
 
-#[derive(Clone, Debug, Eq, PartialEq, Deserialize)]
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub struct Status {
     pub status: PossibleStatuses,
     pub timestamp: String, // TODO: Convert to date type
     pub message: Option<String>,
 }
 
-#[derive(Clone, Debug, Eq, PartialEq, Deserialize)]
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum PossibleStatuses {
     Created,
@@ -26,6 +26,16 @@ pub enum PossibleStatuses {
     Unknown,
 }
 
+impl PossibleStatuses {
+    /// Whether the reservation holds a place on an exam
+    pub fn is_active(&self) -> bool {
+        matches!(
+            self,
+            PossibleStatuses::PlaceReserved | PossibleStatuses::SignupConfirmed
+        )
+    }
+}
+
 impl fmt::Display for PossibleStatuses {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let status_str = match self {
@@ -42,7 +52,7 @@ impl fmt::Display for PossibleStatuses {
     }
 }
 
-#[derive(Clone, Debug, Eq, PartialEq, Deserialize)]
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ReservationStatus {
     pub id: String,
@@ -60,7 +70,7 @@ pub struct ReservationStatus {
     pub awaiting_reschedule: bool,
 }
 
-#[derive(Clone, Debug, Eq, PartialEq, Deserialize)]
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ReservationCandidate {
     pub firstname: String,
@@ -74,7 +84,7 @@ pub struct ReservationCandidate {
 }
 
 // TODO: Convert to an exam enum (thory or pracitce)
-#[derive(Clone, Debug, Eq, PartialEq, Deserialize)]
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct DetailedReservationExam {
     #[serde(deserialize_with = "deserialize_number_from_string")]
@@ -95,7 +105,7 @@ pub struct DetailedReservationExam {
     pub start_date: String,
 }
 
-#[derive(Clone, Debug, Eq, PartialEq, Deserialize)]
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ReservationInvoice {
     pub account_balance: u32,
@@ -1,8 +1,8 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use serde_aux::field_attributes::deserialize_number_from_string;
 use std::num::NonZeroU32;
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct Province {
     #[serde(deserialize_with = "deserialize_number_from_string")]
@@ -14,7 +14,7 @@ pub struct Province {
     pub zoom: i32,
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct WordRescheduleEnabled {
     #[serde(deserialize_with = "deserialize_number_from_string")]
     /// Word center ID
@@ -22,7 +22,7 @@ pub struct WordRescheduleEnabled {
     pub reschedule_enabled: bool,
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct Word {
     #[serde(deserialize_with = "deserialize_number_from_string")]
@@ -36,21 +36,21 @@ pub struct Word {
     pub offline: bool,
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct Language {
     pub code: String,
     pub description: String,
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct SignLanguage {
     pub code: String,
     pub description: String,
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct WordCenters {
     pub provinces: Vec<Province>,
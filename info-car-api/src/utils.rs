@@ -1,4 +1,4 @@
-use crate::types::{Exam, ExamSchedule};
+use crate::types::{Exam, ExamSchedule, Province, Word, WordCenters};
 
 pub fn find_n_practice_exams(schedule: ExamSchedule, number: usize) -> Option<Vec<Exam>> {
     let exams: Vec<Exam> = schedule
@@ -21,3 +21,228 @@ pub fn find_all_practice_exams(schedule: &ExamSchedule) -> Vec<&Exam> {
         .flat_map(|hour| hour.practice_exams.iter())
         .collect()
 }
+
+pub fn find_all_theory_exams(schedule: &ExamSchedule) -> Vec<&Exam> {
+    schedule
+        .schedule
+        .scheduled_days
+        .iter()
+        .flat_map(|day| day.scheduled_hours.iter())
+        .flat_map(|hour| hour.theory_exams.iter())
+        .collect()
+}
+
+/// Lowercases the text and strips the Polish diacritics
+fn normalize(text: &str) -> String {
+    text.to_lowercase()
+        .chars()
+        .map(|c| match c {
+            'ą' => 'a',
+            'ć' => 'c',
+            'ę' => 'e',
+            'ł' => 'l',
+            'ń' => 'n',
+            'ó' => 'o',
+            'ś' => 's',
+            'ź' | 'ż' => 'z',
+            c => c,
+        })
+        .collect()
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut previous = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let current = row[j + 1];
+            row[j + 1] = (previous + usize::from(ca != *cb))
+                .min(row[j] + 1)
+                .min(current + 1);
+            previous = current;
+        }
+    }
+    row[b.len()]
+}
+
+/// Scores how well the query term matches the text. Typos are tolerated in longer terms.
+fn term_score(term: &str, text: &str) -> Option<u32> {
+    let words = || text.split(|c: char| !c.is_alphanumeric());
+    if words().any(|word| word == term) {
+        Some(4)
+    } else if words().any(|word| word.starts_with(term)) {
+        Some(3)
+    } else if text.contains(term) {
+        Some(2)
+    } else {
+        let tolerance = match term.chars().count() {
+            0..=3 => 0,
+            4..=7 => 1,
+            _ => 2,
+        };
+        words()
+            .any(|word| tolerance > 0 && edit_distance(term, word) <= tolerance)
+            .then_some(1)
+    }
+}
+
+/// Finds the WORD centers whose name, address or province match every term of the query, best
+/// matches first
+pub fn search_words<'a>(
+    centers: &'a WordCenters,
+    query: &str,
+) -> Vec<(&'a Word, Option<&'a Province>)> {
+    let terms: Vec<String> = normalize(query)
+        .split_whitespace()
+        .map(str::to_owned)
+        .collect();
+
+    let mut matches: Vec<(u32, &Word, Option<&Province>)> = centers
+        .words
+        .iter()
+        .filter_map(|word| {
+            let province = centers
+                .provinces
+                .iter()
+                .find(|province| province.id == word.province_id);
+            let text = normalize(&format!(
+                "{} {} {}",
+                word.name,
+                word.address,
+                province.map(|p| p.name.as_str()).unwrap_or_default()
+            ));
+            let score = terms
+                .iter()
+                .map(|term| term_score(term, &text))
+                .sum::<Option<u32>>()?;
+            Some((score, word, province))
+        })
+        .collect();
+    matches.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.name.cmp(&b.1.name)));
+
+    matches
+        .into_iter()
+        .map(|(_, word, province)| (word, province))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroU32;
+
+    use super::*;
+
+    fn id(id: u32) -> NonZeroU32 {
+        NonZeroU32::new(id).unwrap()
+    }
+
+    fn province(province_id: u32, name: &str) -> Province {
+        Province {
+            id: id(province_id),
+            name: name.to_owned(),
+            latitude: "0".to_owned(),
+            longitude: "0".to_owned(),
+            zoom: 8,
+        }
+    }
+
+    fn word(word_id: u32, name: &str, address: &str, province_id: u32) -> Word {
+        Word {
+            id: id(word_id),
+            name: name.to_owned(),
+            address: address.to_owned(),
+            latitude: "0".to_owned(),
+            longitude: "0".to_owned(),
+            province_id: id(province_id),
+            offline: false,
+        }
+    }
+
+    fn centers() -> WordCenters {
+        WordCenters {
+            provinces: vec![province(1, "pomorskie"), province(2, "łódzkie")],
+            words: vec![
+                word(3, "WORD Gdańsk", "ul. Równa 19/21, Gdańsk", 1),
+                word(4, "WORD Słupsk", "ul. Jana Pawła II 1, Słupsk", 1),
+                word(5, "WORD Łódź", "ul. Nowy Józefów 70, Łódź", 2),
+                word(6, "WORD Gdynia", "ul. Kielecka 2, Gdańsk Port", 1),
+            ],
+            languages_enums: Vec::new(),
+            sign_language_enums: Vec::new(),
+        }
+    }
+
+    fn search(query: &str) -> Vec<u32> {
+        search_words(&centers(), query)
+            .into_iter()
+            .map(|(word, _)| word.id.get())
+            .collect()
+    }
+
+    #[test]
+    fn normalize_folds_polish_diacritics() {
+        assert_eq!(normalize("ĄĆĘŁŃÓŚŹŻ ąćęłńóśźż"), "acelnoszz acelnoszz");
+        assert_eq!(normalize("Łódź"), "lodz");
+        assert_eq!(normalize("WORD Gdańsk"), "word gdansk");
+    }
+
+    #[test]
+    fn edit_distance_counts_the_edits() {
+        assert_eq!(edit_distance("gdansk", "gdansk"), 0);
+        assert_eq!(edit_distance("gdnask", "gdansk"), 2);
+        assert_eq!(edit_distance("gdask", "gdansk"), 1);
+        assert_eq!(edit_distance("gdanskk", "gdansk"), 1);
+        assert_eq!(edit_distance("", "abc"), 3);
+    }
+
+    #[test]
+    fn term_score_prefers_exact_words() {
+        let text = "word gdansk ul. rowna 19/21";
+        assert_eq!(term_score("gdansk", text), Some(4));
+        assert_eq!(term_score("gdan", text), Some(3));
+        assert_eq!(term_score("dansk", text), Some(2));
+        assert_eq!(term_score("gdamsk", text), Some(1));
+        assert_eq!(term_score("krakow", text), None);
+    }
+
+    #[test]
+    fn term_score_tolerates_typos_in_longer_terms() {
+        // Short terms have to match exactly
+        assert_eq!(term_score("lot", "word lodz"), None);
+        assert_eq!(term_score("slpsk", "word slupsk"), Some(1));
+        assert_eq!(term_score("slupskk", "word slupsk"), Some(1));
+        assert_eq!(term_score("sluupskk", "word slupsk"), Some(1));
+        assert_eq!(term_score("sluupsskk", "word slupsk"), None);
+    }
+
+    #[test]
+    fn search_ignores_diacritics_and_case() {
+        assert_eq!(search("lodz"), vec![5]);
+        assert_eq!(search("ŁÓDŹ"), vec![5]);
+        assert_eq!(search("Slupsk"), vec![4]);
+    }
+
+    #[test]
+    fn search_tolerates_typos() {
+        assert_eq!(search("gdyna"), vec![6]);
+        assert_eq!(search("slupskk"), vec![4]);
+    }
+
+    #[test]
+    fn search_requires_every_term() {
+        assert_eq!(search("gdansk rowna"), vec![3]);
+        assert_eq!(search("pomorskie slupsk"), vec![4]);
+        assert!(search("gdansk krakow").is_empty());
+    }
+
+    #[test]
+    fn search_ranks_the_best_matches_first() {
+        // The name and the address of WORD Gdańsk contain the whole word, Gdynia only the address
+        assert_eq!(search("gdansk"), vec![3, 6]);
+        // Equally good matches are sorted by name
+        assert_eq!(search("pomorskie"), vec![3, 6, 4]);
+        assert_eq!(search("gdy"), vec![6]);
+    }
+}